@@ -0,0 +1,185 @@
+use super::{Theme, TodoError};
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Name of a project configuration file
+static PROJECT: &str = ".todotree.toml";
+
+/// Kind of the value of a configuration key
+enum Kind {
+    /// true or false, as an option without argument
+    Flag,
+    /// a string, as an option with an argument
+    Text,
+    /// an integer
+    Number,
+    /// a string or an array of strings, as an option given several times
+    List,
+    /// a list of paths, relative to the configuration file
+    Paths,
+}
+
+/// The keys of a configuration file, named after the long options
+static KEYS: [(&str, Kind); 20] = [
+    ("auto-add", Kind::Flag),
+    ("by-priority", Kind::Flag),
+    ("critical-path", Kind::Flag),
+    ("depth", Kind::Number),
+    ("exclude-tag", Kind::List),
+    ("format", Kind::Text),
+    ("hide-comment", Kind::Flag),
+    ("hide-done", Kind::Flag),
+    ("hide-owner", Kind::Flag),
+    ("input", Kind::Paths),
+    ("next", Kind::Flag),
+    ("no-color", Kind::Flag),
+    ("owner", Kind::List),
+    ("priority", Kind::Text),
+    ("reverse", Kind::Flag),
+    ("separator", Kind::Text),
+    ("sort", Kind::Flag),
+    ("sort-by", Kind::Text),
+    ("tag", Kind::List),
+    ("theme", Kind::Text),
+];
+
+/// Default values of the command line options, read from configuration files
+#[derive(Default, Clone, Debug)]
+pub struct Config {
+    /// the values by key, the ones of the later files replacing the others
+    values: Table,
+    /// the 'theme' key, a name, a path or a table
+    theme: Option<Theme>,
+}
+
+impl Config {
+    /// Reads $XDG_CONFIG_HOME/todotree/config.toml, then the first
+    /// .todotree.toml found from the current directory up to the root.
+    pub fn load() -> Result<Self, TodoError> {
+        let mut paths = vec![];
+        let home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| {
+                env::var_os("HOME").map(|h| Path::new(&h).join(".config"))
+            });
+        if let Some(home) = home {
+            paths.push(home.join("todotree").join("config.toml"));
+        }
+        let cwd = env::current_dir()?;
+        if let Some(dir) = cwd.ancestors().find(|d| d.join(PROJECT).is_file()) {
+            paths.push(dir.join(PROJECT));
+        }
+        let paths: Vec<PathBuf> =
+            paths.into_iter().filter(|p| p.is_file()).collect();
+        Config::from_files(&paths)
+    }
+
+    /// Reads configuration files, the later ones overriding the others.
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self, TodoError> {
+        let mut config = Config::default();
+        for path in paths {
+            config.read(path)?;
+        }
+        Ok(config)
+    }
+
+    fn read(&mut self, path: &Path) -> Result<(), TodoError> {
+        let file = path.display().to_string();
+        let invalid = |reason: String| TodoError::Toml {
+            file: file.clone(),
+            reason,
+        };
+        let table: Table = read_to_string(path)?
+            .parse()
+            .map_err(|e: toml::de::Error| invalid(e.message().to_string()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let relative = |p: &str| dir.join(p).display().to_string();
+        for (key, value) in table {
+            let kind = match KEYS.iter().find(|(k, _)| *k == key) {
+                Some((_, kind)) => kind,
+                None => return Err(invalid(format!("unknown key '{}'", key))),
+            };
+            let wrong = |what: &str| invalid(format!("'{}' {}", key, what));
+            let value = match (kind, value) {
+                (_, Value::Table(table)) if key == "theme" => {
+                    self.theme = Some(Theme::from_table(&table, &file)?);
+                    continue;
+                }
+                (_, Value::String(name)) if key == "theme" => {
+                    let name = match name.ends_with(".toml") {
+                        true => relative(&name),
+                        false => name,
+                    };
+                    self.theme = Some(Theme::named_or_file(&name)?);
+                    continue;
+                }
+                (Kind::Flag, v @ Value::Boolean(_)) => v,
+                (Kind::Text, v @ Value::String(_)) => v,
+                (Kind::Number, Value::Integer(n)) => {
+                    Value::String(n.to_string())
+                }
+                (Kind::List | Kind::Paths, Value::String(s)) => {
+                    Value::Array(vec![Value::String(s)])
+                }
+                (Kind::List | Kind::Paths, Value::Array(items))
+                    if items.iter().all(|v| v.is_str()) =>
+                {
+                    Value::Array(items)
+                }
+                (Kind::Flag, _) => return Err(wrong("should be a boolean")),
+                (Kind::Text, _) => return Err(wrong("should be a string")),
+                (Kind::Number, _) => return Err(wrong("should be an integer")),
+                _ => return Err(wrong("should be strings")),
+            };
+            let value = match (kind, value) {
+                (Kind::Paths, Value::Array(items)) => Value::Array(
+                    items
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(|p| Value::String(relative(p)))
+                        .collect(),
+                ),
+                (_, value) => value,
+            };
+            self.values.insert(key, value);
+        }
+        Ok(())
+    }
+
+    /// Returns the keys that are flags, such as 'sort'.
+    pub fn flags() -> impl Iterator<Item = &'static str> {
+        KEYS.iter()
+            .filter(|(_, kind)| matches!(kind, Kind::Flag))
+            .map(|(key, _)| *key)
+    }
+
+    /// Returns true if a flag, such as 'sort', is set to true.
+    pub fn flag(&self, key: &str) -> bool {
+        self.values.get(key).and_then(|v| v.as_bool()) == Some(true)
+    }
+
+    /// Returns the value of a key such as 'format' or 'depth'.
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.values.get(key)?.as_str().map(String::from)
+    }
+
+    /// Returns the values of a key such as 'input' or 'tag'.
+    pub fn strings(&self, key: &str) -> Vec<String> {
+        match self.values.get(key).and_then(|v| v.as_array()) {
+            Some(items) => items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the theme, if set.
+    pub fn theme(&self) -> Option<&Theme> {
+        self.theme.as_ref()
+    }
+}
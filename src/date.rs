@@ -0,0 +1,79 @@
+use super::TodoError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A calendar date, written as YYYY-MM-DD
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    /// Creates a date, or None if there is no such day.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        match day >= 1 && day <= days {
+            true => Some(Date { year, month, day }),
+            false => None,
+        }
+    }
+
+    /// Returns the local date of now.
+    pub fn today() -> Self {
+        let mut tm: libc::tm;
+        unsafe {
+            let now = libc::time(std::ptr::null_mut());
+            tm = std::mem::zeroed();
+            libc::localtime_r(&now, &mut tm);
+        }
+        Date {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u32 + 1,
+            day: tm.tm_mday as u32,
+        }
+    }
+}
+
+/// Returns the date of a '- ! ' or '- > ' line, without its leading word.
+pub(crate) fn date_text<'a>(text: &'a str, word: &str) -> &'a str {
+    match text.trim().split_once(' ') {
+        Some((w, date)) if w == word => date.trim(),
+        _ => text.trim(),
+    }
+}
+
+impl FromStr for Date {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || TodoError::BadDate {
+            text: s.to_string(),
+        };
+        let parts: Vec<&str> = s.split('-').collect();
+        if parts.len() != 3
+            || parts[0].len() != 4
+            || parts[1].len() != 2
+            || parts[2].len() != 2
+            || !s.chars().all(|c| c == '-' || c.is_ascii_digit())
+        {
+            return Err(bad());
+        }
+        let num = |i: usize| parts[i].parse::<u32>().map_err(|_| bad());
+        Date::new(num(0)? as i32, num(1)?, num(2)?).ok_or_else(bad)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
@@ -0,0 +1,781 @@
+//! Edits todos in the markdown files in place.
+use super::{
+    Format, InputFormat, ROOT, Status, TodoError,
+    date::date_text,
+    todo::Todo,
+    tree::{Tree, TreeOptions, escape},
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, read_to_string};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// How to change the completion of a todo
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Mark {
+    Done,
+    Undone,
+    Toggle,
+}
+
+/// Marks a todo completed or not, by rewriting its '# ' line only.
+///
+/// A todo cannot be done before its dependencies, nor undone after a todo
+/// depending on it, unless forced. Returns whether it is completed now.
+pub fn mark(
+    opts: &TreeOptions,
+    name: &str,
+    mark: Mark,
+    force: bool,
+) -> Result<bool, TodoError> {
+    let tree = load(opts)?;
+    let todos = by_name(&tree);
+    let todo = find(&todos, name)?.borrow();
+    let completed = todo.status == Status::Completed;
+    let done = match mark {
+        Mark::Done => true,
+        Mark::Undone => false,
+        Mark::Toggle => !completed,
+    };
+    if done == completed {
+        return Ok(done);
+    }
+    if !force && done {
+        let notdonedeps: Vec<String> = todo
+            .dependencies
+            .iter()
+            .map(|d| d.replace("~", ""))
+            .filter(|d| {
+                todos.get(d).is_none_or(|t| !t.borrow().status.is_done())
+            })
+            .collect();
+        if !notdonedeps.is_empty() {
+            return Err(TodoError::Incomplete {
+                name: todo.name.clone(),
+                dependencies: notdonedeps,
+            }
+            .at(&todo.position));
+        }
+    } else if !force {
+        for parent in todos.values() {
+            let parent = parent.borrow();
+            if parent.status == Status::Completed
+                && parent
+                    .dependencies
+                    .iter()
+                    .any(|d| d.replace("~", "") == todo.name)
+            {
+                return Err(TodoError::Incomplete {
+                    name: parent.name.clone(),
+                    dependencies: vec![todo.name.clone()],
+                }
+                .at(parent.dep_position(&todo.name)));
+            }
+        }
+    }
+    let base = name_in_file(opts, &todo)?;
+    // a completed todo has no other status
+    rewrite_block(&todo.file, &base, |block| {
+        let line = &block[0];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let text = heading(line).map_or(base.clone(), |(_, text, _)| text);
+        block[0] = match done {
+            true => format!("{}# ~~{}~~", indent, text),
+            false => format!("{}# {}", indent, text),
+        };
+        if done {
+            block.retain(|ln| !ln.trim().starts_with("- * "));
+        }
+    })?;
+    Ok(done)
+}
+
+/// Appends a new todo to the first input.
+pub fn add(
+    opts: &TreeOptions,
+    name: &str,
+    owners: &[String],
+    dependencies: &[String],
+    comments: &[String],
+) -> Result<(), TodoError> {
+    let tree = load(opts)?;
+    let todos = by_name(&tree);
+    let mdfile = &opts.inputs[0];
+    let is_json = match opts.input_format {
+        Some(fmt) => fmt == InputFormat::Json,
+        None => mdfile.ends_with(".json"),
+    };
+    if is_json {
+        return Err(TodoError::NotMarkdown {
+            file: mdfile.to_string(),
+        });
+    }
+    let suffix = suffix(opts, mdfile);
+    if name.is_empty() || name == ROOT {
+        return Err(TodoError::ReservedName);
+    } else if name.contains('@') {
+        return Err(TodoError::AtInName {
+            name: name.to_string(),
+        });
+    }
+    let comments: Vec<String> = comments
+        .iter()
+        .flat_map(|c| c.lines())
+        .map(|c| c.trim_end().to_string())
+        .collect();
+    let mut unique: Vec<String> = Vec::new();
+    for owner in owners.iter().flat_map(|o| o.split(',')) {
+        let owner = owner.trim().trim_start_matches('@');
+        if owner.is_empty() {
+            continue;
+        } else if unique.iter().any(|o| o == owner) {
+            return Err(TodoError::DuplicatedOwner {
+                name: name.to_string(),
+                owner: owner.to_string(),
+            });
+        }
+        unique.push(owner.to_string());
+    }
+    let todo = Todo::new(
+        name.to_string(),
+        Status::Pending,
+        unique,
+        comments,
+        dependencies.to_vec(),
+        Vec::new(),
+    )?;
+    if let Some(dup) = todos.get(&format!("{}{}", name, suffix)) {
+        return Err(TodoError::Duplicated {
+            name: name.to_string(),
+        }
+        .at(&dup.borrow().position));
+    }
+    for dep in &todo.dependencies {
+        let nom = dep.replace("~", "");
+        if nom == name {
+            return Err(TodoError::SelfDependency {
+                name: nom.to_string(),
+            });
+        }
+        let full = match nom.contains('@') {
+            true => nom.clone(),
+            false => format!("{}{}", nom, suffix),
+        };
+        match todos.get(&full) {
+            Some(_) if dep.contains('~') => {
+                return Err(TodoError::CompletedDependency {
+                    name: nom.to_string(),
+                    parent: name.to_string(),
+                });
+            }
+            None if !opts.auto_add => {
+                return Err(TodoError::Missing {
+                    name: nom.to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    let mut content = read_to_string(mdfile)?;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    if !content.is_empty() && !content.ends_with("\n\n") {
+        content.push('\n');
+    }
+    content.push_str(&format!("# {}\n", todo.name));
+    for owner in &todo.owners {
+        content.push_str(&format!("- @ {}\n", owner));
+    }
+    if !todo.dependencies.is_empty() {
+        content.push_str(&format!("- : {}\n", todo.dependencies.join(" ")));
+    }
+    for comt in &todo.comment {
+        content.push_str(&format!("- % {}\n", comt));
+    }
+    write_file(mdfile, &content)
+}
+
+/// Makes a todo depend on another one, by editing its '- : ' line.
+pub fn link(
+    opts: &TreeOptions,
+    parent: &str,
+    child: &str,
+) -> Result<(), TodoError> {
+    let tree = load(opts)?;
+    let todos = by_name(&tree);
+    let parent = find(&todos, parent)?.borrow();
+    let base = name_in_file(opts, &parent)?;
+    let (full, dep) = match find(&todos, &child.replace("~", "")) {
+        Ok(todo) => {
+            let todo = todo.borrow();
+            if child.contains('~') {
+                return Err(TodoError::CompletedDependency {
+                    name: todo.name.clone(),
+                    parent: parent.name.clone(),
+                });
+            }
+            if parent.status == Status::Completed && !todo.status.is_done() {
+                return Err(TodoError::Incomplete {
+                    name: parent.name.clone(),
+                    dependencies: vec![todo.name.clone()],
+                }
+                .at(parent.dep_position(&todo.name)));
+            }
+            let dep = match todo.file == parent.file {
+                true => todo.name.split('@').next().unwrap_or_default(),
+                false => &todo.name,
+            };
+            (todo.name.clone(), dep.to_string())
+        }
+        Err(_) if opts.auto_add => (
+            child.replace("~", "") + &suffix(opts, &parent.file),
+            child.into(),
+        ),
+        Err(e) => return Err(e),
+    };
+    if full == parent.name {
+        return Err(TodoError::SelfDependency {
+            name: full.to_string(),
+        });
+    }
+    if parent
+        .dependencies
+        .iter()
+        .any(|d| d.replace("~", "") == full)
+    {
+        return Err(TodoError::AlreadyDepends {
+            name: parent.name.clone(),
+            dependency: full.to_string(),
+        }
+        .at(parent.dep_position(&full)));
+    }
+    if let Some(chain) = dependency_path(&todos, &full, &parent.name) {
+        return Err(TodoError::Loop { cycle: chain }.at(&parent.position));
+    }
+    rewrite_block(&parent.file, &base, |block| {
+        match block.iter().rposition(|ln| is_dependency_line(ln)) {
+            Some(idx) => block[idx] = format!("{} {}", block[idx], dep),
+            None => {
+                let idx = block
+                    .iter()
+                    .skip(1)
+                    .position(|ln| !ln.trim().starts_with("- @ "))
+                    .map_or(block.len(), |i| i + 1);
+                block.insert(idx, format!("- : {}", dep));
+            }
+        }
+    })
+}
+
+/// Removes a dependency of a todo from its '- : ' lines.
+pub fn unlink(
+    opts: &TreeOptions,
+    parent: &str,
+    child: &str,
+) -> Result<(), TodoError> {
+    let tree = load(opts)?;
+    let todos = by_name(&tree);
+    let parent = find(&todos, parent)?.borrow();
+    let base = name_in_file(opts, &parent)?;
+    let sfx = suffix(opts, &parent.file);
+    let child = child.replace("~", "");
+    // a name without '@' is first looked up in the file of the parent
+    let local = format!("{}{}", child, sfx);
+    let child = match find(&todos, &child) {
+        _ if !child.contains('@') && todos.contains_key(&local) => local,
+        Ok(todo) => todo.borrow().name.clone(),
+        Err(_) => child,
+    };
+    let same = |dep: &str| {
+        let dep = escape(&dep.replace("~", ""));
+        match dep.contains('@') {
+            true => dep == child,
+            false => format!("{}{}", dep, sfx) == child,
+        }
+    };
+    if !parent.dependencies.iter().any(|d| same(d)) {
+        return Err(TodoError::NotDependent {
+            name: parent.name.clone(),
+            dependency: child.to_string(),
+        }
+        .at(&parent.position));
+    }
+    rewrite_block(&parent.file, &base, |block| {
+        block.retain_mut(|ln| {
+            if !is_dependency_line(ln) {
+                return true;
+            }
+            let old: Vec<&str> = ln.trim()[3..].split_whitespace().collect();
+            let kept: Vec<&str> =
+                old.iter().copied().filter(|d| !same(d)).collect();
+            if kept.len() == old.len() {
+                return true;
+            } else if kept.is_empty() {
+                return false;
+            }
+            let indent = &ln[..ln.len() - ln.trim_start().len()];
+            *ln = format!("{}- : {}", indent, kept.join(" "));
+            true
+        });
+    })
+}
+
+/// Renames a todo, in its '# ' line and all dependency lists of all inputs.
+///
+/// All files are written only after all of them are rewritten.
+pub fn rename(
+    opts: &TreeOptions,
+    old: &str,
+    new: &str,
+) -> Result<(), TodoError> {
+    let tree = load(opts)?;
+    let todos = by_name(&tree);
+    let todo = find(&todos, old)?.borrow();
+    let base = name_in_file(opts, &todo)?;
+    let sfx = suffix(opts, &todo.file);
+    if new.is_empty() || new == ROOT {
+        return Err(TodoError::ReservedName);
+    } else if new.contains('@') {
+        return Err(TodoError::AtInName {
+            name: new.to_string(),
+        });
+    }
+    Todo::new(
+        new.to_string(),
+        Status::Pending,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+    )?;
+    if let Some(dup) = todos.get(&format!("{}{}", new, sfx)) {
+        return Err(TodoError::Duplicated {
+            name: new.to_string(),
+        }
+        .at(&dup.borrow().position));
+    }
+    let mut contents = Vec::new();
+    for mdfile in &opts.inputs {
+        let is_json = match opts.input_format {
+            Some(fmt) => fmt == InputFormat::Json,
+            None => mdfile.ends_with(".json"),
+        };
+        if is_json {
+            return Err(TodoError::NotMarkdown {
+                file: mdfile.to_string(),
+            });
+        }
+        let same_file = *mdfile == todo.file;
+        let rename_dep = |dep: &str| {
+            let nom = dep.trim_start_matches('~');
+            let tilde = &dep[..dep.len() - nom.len()];
+            // the names are unescaped in the tree
+            let nom = escape(nom);
+            if same_file && nom == base {
+                format!("{}{}", tilde, new)
+            } else if !sfx.is_empty() && nom == format!("{}{}", base, sfx) {
+                format!("{}{}{}", tilde, new, sfx)
+            } else {
+                dep.to_string()
+            }
+        };
+        let buffer = read_to_string(mdfile)?;
+        let content = rewrite_lines(&buffer, |line| {
+            if same_file && heading(line).is_some_and(|(nm, ..)| nm == base) {
+                let raw = line.trim()[2..].trim();
+                let prefix =
+                    &raw[..raw.len() - raw.trim_start_matches('~').len()];
+                let postfix = &raw[raw.trim_end_matches('~').len()..];
+                let indent = &line[..line.len() - line.trim_start().len()];
+                Some(format!("{}# {}{}{}", indent, prefix, new, postfix))
+            } else if is_dependency_line(line) {
+                let old: Vec<&str> =
+                    line.trim()[3..].split_whitespace().collect();
+                let deps: Vec<String> =
+                    old.iter().map(|d| rename_dep(d)).collect();
+                if deps == old {
+                    return None;
+                }
+                let indent = &line[..line.len() - line.trim_start().len()];
+                Some(format!("{}- : {}", indent, deps.join(" ")))
+            } else {
+                None
+            }
+        });
+        if content != buffer {
+            contents.push((mdfile, content));
+        }
+    }
+    let mut staged = Vec::new();
+    for (mdfile, content) in contents {
+        staged.push((mdfile, stage_file(mdfile, &content)?));
+    }
+    for (mdfile, tmp) in staged {
+        fs::rename(tmp, mdfile)?;
+    }
+    Ok(())
+}
+
+/// Rewrites the markdown inputs in the canonical form of `format_md`.
+///
+/// Returns the files that are not canonical, which are left alone if
+/// `check` is set.
+pub fn fmt(opts: &TreeOptions, check: bool) -> Result<Vec<String>, TodoError> {
+    load(opts)?;
+    let mut changed = Vec::new();
+    for mdfile in &opts.inputs {
+        let is_json = match opts.input_format {
+            Some(fmt) => fmt == InputFormat::Json,
+            None => mdfile.ends_with(".json"),
+        };
+        if is_json {
+            continue;
+        }
+        let buffer = read_to_string(mdfile)?;
+        let content = format_md(&buffer);
+        if content != buffer {
+            if !check {
+                write_file(mdfile, &content)?;
+            }
+            changed.push(mdfile.clone());
+        }
+    }
+    Ok(changed)
+}
+
+/// Tells whether a word of the command line names a todo of the inputs, so
+/// that a todo named like a command, such as 'check', is shown instead.
+pub fn is_todo(opts: &TreeOptions, word: &str) -> bool {
+    load(opts).is_ok_and(|tree| find(&by_name(&tree), word).is_ok())
+}
+
+/// Returns a markdown file in the canonical form.
+///
+/// Each todo has its '# ' line with '~~' if completed, then its '- @ ' lines
+/// with one owner each, '- * ', '- ^ ', '- = ', a single '- # ', '- ! due',
+/// '- > start', a single '- : ', its '- % ' lines and its '- $ ' lines, then
+/// the other lines following it in the original order. Todos stay in the
+/// original order.
+pub fn format_md(buffer: &str) -> String {
+    /// The canonical lines of a todo, or of the lines before the first one
+    #[derive(Default)]
+    struct Block {
+        heading: Option<String>,
+        owners: Vec<String>,
+        status: Option<String>,
+        priority: Option<String>,
+        estimate: Option<String>,
+        tags: Vec<String>,
+        due: Option<String>,
+        start: Option<String>,
+        dependencies: Vec<String>,
+        comments: Vec<String>,
+        recipe: Vec<String>,
+        auxilaries: Vec<String>,
+    }
+    impl Block {
+        fn write(self, content: &mut String) {
+            let tags = match self.tags.is_empty() {
+                true => None,
+                false => Some(format!("- # {}", self.tags.join(" "))),
+            };
+            let deps = match self.dependencies.is_empty() {
+                true => None,
+                false => Some(format!("- : {}", self.dependencies.join(" "))),
+            };
+            for ln in self
+                .heading
+                .into_iter()
+                .chain(self.owners)
+                .chain(self.status)
+                .chain(self.priority)
+                .chain(self.estimate)
+                .chain(tags)
+                .chain(self.due)
+                .chain(self.start)
+                .chain(deps)
+                .chain(self.comments)
+                .chain(self.recipe)
+                .chain(self.auxilaries)
+            {
+                content.push_str(&ln);
+                content.push('\n');
+            }
+        }
+    }
+    let mut content = String::new();
+    let mut block = Block::default();
+    for line in buffer.lines() {
+        let ln = line.trim();
+        if let Some((_, name, completed)) = heading(ln) {
+            std::mem::take(&mut block).write(&mut content);
+            block.heading = Some(match completed {
+                true => format!("# ~~{}~~", name),
+                false => format!("# {}", name),
+            });
+        } else if let Some(owners) = ln.strip_prefix("- @ ") {
+            for owner in owners.split(',').map(|o| o.trim()) {
+                if !owner.is_empty() {
+                    block.owners.push(format!("- @ {}", owner));
+                }
+            }
+        } else if let Some(status) = ln.strip_prefix("- * ") {
+            block.status = Some(format!("- * {}", status.trim()));
+        } else if let Some(priority) = ln.strip_prefix("- ^ ") {
+            block.priority = Some(format!("- ^ {}", priority.trim()));
+        } else if let Some(estimate) = ln.strip_prefix("- = ") {
+            block.estimate = Some(format!("- = {}", estimate.trim()));
+        } else if let Some(tags) = ln.strip_prefix("- # ") {
+            block
+                .tags
+                .extend(tags.split_whitespace().map(|t| t.to_string()));
+        } else if let Some(due) = ln.strip_prefix("- ! ") {
+            block.due = Some(format!("- ! due {}", date_text(due, "due")));
+        } else if let Some(start) = ln.strip_prefix("- > ") {
+            block.start =
+                Some(format!("- > start {}", date_text(start, "start")));
+        } else if let Some(deps) = ln.strip_prefix("- : ") {
+            block
+                .dependencies
+                .extend(deps.split_whitespace().map(|d| d.to_string()));
+        } else if ln.starts_with("- % ") || ln == "- %" {
+            block.comments.push(ln.to_string());
+        } else if let Some(command) = ln.strip_prefix("- $ ") {
+            block.recipe.push(format!("- $ {}", command.trim()));
+        } else {
+            block.auxilaries.push(line.trim_end().to_string());
+        }
+    }
+    block.write(&mut content);
+    content
+}
+
+/// Returns the dependency chain from one todo to another, if any.
+pub(crate) fn dependency_path(
+    todos: &BTreeMap<String, Rc<RefCell<Todo>>>,
+    from: &str,
+    to: &str,
+) -> Option<Vec<String>> {
+    fn walk(
+        todos: &BTreeMap<String, Rc<RefCell<Todo>>>,
+        from: &str,
+        to: &str,
+        visited: &mut BTreeSet<String>,
+        chain: &mut Vec<String>,
+    ) -> bool {
+        chain.push(from.to_string());
+        if from == to {
+            return true;
+        }
+        if visited.insert(from.to_string())
+            && let Some(todo) = todos.get(from)
+        {
+            for dep in &todo.borrow().dependencies {
+                if walk(todos, &dep.replace("~", ""), to, visited, chain) {
+                    return true;
+                }
+            }
+        }
+        chain.pop();
+        false
+    }
+    let mut chain = Vec::new();
+    match walk(todos, from, to, &mut BTreeSet::new(), &mut chain) {
+        true => Some(chain),
+        false => None,
+    }
+}
+
+/// Returns the suffix that todo names in a file get, for several inputs.
+fn suffix(opts: &TreeOptions, mdfile: &str) -> String {
+    match opts.inputs.len() {
+        1 => String::new(),
+        _ => format!(
+            "@{}",
+            Path::new(mdfile).file_stem().unwrap().to_string_lossy()
+        ),
+    }
+}
+
+/// Tells whether a line lists dependencies.
+fn is_dependency_line(line: &str) -> bool {
+    line.trim().starts_with("- : ")
+}
+
+/// Builds a tree of all todos, ignoring the options that hide or draw some.
+pub(crate) fn load(opts: &TreeOptions) -> Result<Tree, TodoError> {
+    let mut opts = opts
+        .clone()
+        .targets(Vec::new())
+        .owners(Vec::new())
+        .tags(Vec::new())
+        .exclude_tags(Vec::new())
+        .hide_done(false)
+        .depth(0)
+        .reverse(false)
+        .critical_path(false)
+        .format(Format::Md)
+        // nothing is drawn, so the tree can be as wide as it is
+        .term_width(usize::MAX);
+    opts.priority = None;
+    opts.upstream = None;
+    Tree::new(&opts)
+}
+
+/// Maps the names to all todos in a tree.
+pub(crate) fn by_name(tree: &Tree) -> BTreeMap<String, Rc<RefCell<Todo>>> {
+    tree.todos()
+        .into_iter()
+        .map(|t| (t.borrow().name.clone(), Rc::clone(&t)))
+        .collect()
+}
+
+/// Finds a todo by its name, with or without the '@file' suffix.
+pub(crate) fn find<'a>(
+    todos: &'a BTreeMap<String, Rc<RefCell<Todo>>>,
+    name: &str,
+) -> Result<&'a Rc<RefCell<Todo>>, TodoError> {
+    if let Some(todo) = todos.get(name) {
+        return Ok(todo);
+    }
+    let found: Vec<&Rc<RefCell<Todo>>> = todos
+        .iter()
+        .filter(|(k, _)| k.split('@').next() == Some(name))
+        .map(|(_, v)| v)
+        .collect();
+    match found.len() {
+        0 => Err(TodoError::Missing {
+            name: name.to_string(),
+        }),
+        1 => Ok(found[0]),
+        _ => Err(TodoError::Ambiguous {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Returns the name of a todo in its markdown file, without '@file'.
+pub(crate) fn name_in_file(
+    opts: &TreeOptions,
+    todo: &Todo,
+) -> Result<String, TodoError> {
+    if todo.file.is_empty() {
+        return Err(TodoError::AutoAdded {
+            name: todo.name.clone(),
+        });
+    }
+    let is_json = match opts.input_format {
+        Some(fmt) => fmt == InputFormat::Json,
+        None => todo.file.ends_with(".json"),
+    };
+    if is_json {
+        return Err(TodoError::NotMarkdown {
+            file: todo.file.to_string(),
+        });
+    }
+    Ok(todo.name.split('@').next().unwrap_or_default().to_string())
+}
+
+/// Parses a '# ' line into the todo name, unescaped as `readmd` does, the
+/// name as written, and whether it is completed.
+pub(crate) fn heading(line: &str) -> Option<(String, String, bool)> {
+    let escaped = escape(line);
+    let ln = escaped.trim();
+    if !ln.starts_with("# ") {
+        return None;
+    }
+    let name = ln[2..].trim();
+    let text = line.trim().get(2..).unwrap_or_default().trim();
+    Some((
+        name.replace("~", ""),
+        text.replace("~", ""),
+        name.starts_with('~'),
+    ))
+}
+
+/// Returns the buffer with the lines that `edit` returns a replacement for.
+///
+/// Line endings and all other lines are kept as they are.
+fn rewrite_lines<F>(buffer: &str, mut edit: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut content = String::new();
+    for line in buffer.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\r', '\n']);
+        match edit(body) {
+            Some(new) => {
+                content.push_str(&new);
+                content.push_str(&line[body.len()..]);
+            }
+            None => content.push_str(line),
+        }
+    }
+    content
+}
+
+/// Rewrites the lines of a todo, from its '# ' line to the next one.
+fn rewrite_block<F>(path: &str, name: &str, edit: F) -> Result<(), TodoError>
+where
+    F: FnOnce(&mut Vec<String>),
+{
+    let buffer = read_to_string(path)?;
+    let lines: Vec<&str> = buffer.split_inclusive('\n').collect();
+    let start = lines
+        .iter()
+        .position(|ln| heading(ln).is_some_and(|(nm, ..)| nm == name))
+        .ok_or_else(|| TodoError::Missing {
+            name: name.to_string(),
+        })?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|ln| heading(ln).is_some())
+        .map_or(lines.len(), |i| start + 1 + i);
+    let eol = match lines[start].ends_with("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+    let mut block: Vec<String> = lines[start..end]
+        .iter()
+        .map(|ln| ln.trim_end_matches(['\r', '\n']).to_string())
+        .collect();
+    // the last line of a file may have no newline
+    let last_eol = lines[end - 1].ends_with('\n');
+    edit(&mut block);
+    let mut content: String = lines[..start].concat();
+    for (idx, ln) in block.iter().enumerate() {
+        content.push_str(ln);
+        if idx + 1 < block.len() || last_eol {
+            content.push_str(eol);
+        }
+    }
+    content.push_str(&lines[end..].concat());
+    write_file(path, &content)
+}
+
+/// Replaces a file atomically, keeping its permissions.
+pub(crate) fn write_file(path: &str, content: &str) -> Result<(), TodoError> {
+    let tmp = stage_file(path, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Writes the new content of a file next to it, for renaming it over later.
+fn stage_file(path: &str, content: &str) -> Result<PathBuf, TodoError> {
+    let tmp = match Path::new(path).file_name() {
+        Some(nm) => Path::new(path)
+            .with_file_name(format!(".{}.tmp", nm.to_string_lossy())),
+        None => {
+            return Err(TodoError::NotFile {
+                file: path.to_string(),
+            });
+        }
+    };
+    fs::write(&tmp, content)?;
+    if let Ok(meta) = fs::metadata(path) {
+        fs::set_permissions(&tmp, meta.permissions())?;
+    }
+    Ok(tmp)
+}
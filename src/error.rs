@@ -0,0 +1,537 @@
+use super::{ROOT, json_quote};
+use getopts::Fail;
+use std::fmt;
+use std::io;
+use std::num::ParseIntError;
+use unicode_width::UnicodeWidthStr;
+
+/// A position in an input file
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Position {
+    pub(crate) file: String,
+    /// 1-based, 0 if unknown
+    pub(crate) line: usize,
+    /// 1-based, in characters
+    pub(crate) column: usize,
+    /// the line, with tabs expanded to spaces
+    pub(crate) text: String,
+    /// display width of the highlighted text
+    width: usize,
+}
+
+impl Position {
+    /// Creates the position of token in a line, or of the line if not found.
+    pub(crate) fn new(
+        file: &str,
+        line: usize,
+        text: &str,
+        token: &str,
+    ) -> Self {
+        let text = text.replace('\t', " ");
+        let start = match text.find(token).filter(|_| !token.is_empty()) {
+            Some(idx) => idx,
+            None => text.len() - text.trim_start().len(),
+        };
+        let width = match text[start..].starts_with(token) {
+            true => token.width(),
+            false => text.trim().width(),
+        };
+        Position {
+            file: file.to_string(),
+            line,
+            column: text[..start].chars().count() + 1,
+            text,
+            width: width.max(1),
+        }
+    }
+
+    /// Returns the file, empty if unknown.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the 1-based line number, 0 if unknown.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the 1-based column number, in characters.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns the line.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// An error, with one variant per error code
+#[derive(Debug)]
+pub enum TodoError {
+    /// ERR-001
+    SpaceInName { name: String, space: char },
+    /// ERR-002, the todos of a loop, each depending on the next one, and the
+    /// last one on the first one
+    Loop { cycle: Vec<String> },
+    /// ERR-003
+    Missing { name: String },
+    /// ERR-004, a completed todo and its dependencies not completed
+    Incomplete {
+        name: String,
+        dependencies: Vec<String>,
+    },
+    /// ERR-005
+    ScreenWidth { width: usize, needed: usize },
+    /// ERR-006, the value of '-f'
+    Format { value: String },
+    /// ERR-007, the loops as in Loop
+    NoRoot { cycles: Vec<Vec<String>> },
+    /// ERR-008, the todo or team listing an owner twice
+    DuplicatedOwner { name: String, owner: String },
+    /// ERR-009
+    ReservedName,
+    /// ERR-010
+    NoTodo,
+    /// ERR-011, a todo with its own '# ' line, marked '~' by parent
+    CompletedDependency { name: String, parent: String },
+    /// ERR-012, a dependency marked '~' by name but not by other
+    InconsistentDependency {
+        name: String,
+        dependency: String,
+        other: String,
+    },
+    /// ERR-013
+    MissingHeading,
+    /// ERR-014
+    Duplicated { name: String },
+    /// ERR-015, the '# ' line
+    Heading { line: String },
+    /// ERR-016
+    SelfDependency { name: String },
+    /// ERR-017
+    AtInName { name: String },
+    /// ERR-018
+    SlashInName { name: String },
+    /// ERR-019
+    Inotify(io::Error),
+    /// ERR-020
+    Reverse,
+    /// ERR-022
+    NoSuchOwner { owner: String },
+    /// ERR-023, the value of '--input-format'
+    InputFormat { value: String },
+    /// ERR-024
+    Json { file: String, reason: String },
+    /// ERR-025
+    AutoAdded { name: String },
+    /// ERR-026
+    NotMarkdown { file: String },
+    /// ERR-027
+    Ambiguous { name: String },
+    /// ERR-028
+    NotFile { file: String },
+    /// ERR-029
+    Arguments { command: String },
+    /// ERR-030
+    AlreadyDepends { name: String, dependency: String },
+    /// ERR-031
+    NotDependent { name: String, dependency: String },
+    /// ERR-032
+    NotFormatted { file: String },
+    /// ERR-033, the problems found by 'check'
+    Problems { errors: Vec<TodoError> },
+    /// ERR-034, displayed without its code
+    Io(io::Error),
+    /// ERR-035, a wrong command line option, displayed without its code
+    Usage(String),
+    /// ERR-036, the text that is not a YYYY-MM-DD date
+    BadDate { text: String },
+    /// ERR-037, the todo with several due or start dates
+    MultipleDates { name: String },
+    /// ERR-038, the text that is not a priority
+    BadPriority { text: String },
+    /// ERR-039, the todo with several priorities
+    MultiplePriorities { name: String },
+    /// ERR-040, the text that is not a status
+    BadStatus { text: String },
+    /// ERR-041, the todo with several statuses, or with '~' and a status
+    MultipleStatuses { name: String },
+    /// ERR-042, the value of '--theme'
+    Theme { value: String },
+    /// ERR-043, the text that is not a color
+    BadColor { text: String },
+    /// ERR-044
+    Toml { file: String, reason: String },
+    /// ERR-045, a todo to run with neither recipe nor dependencies
+    NoRecipe { name: String },
+    /// ERR-046, the command that failed, and its exit code if any
+    RecipeFailed {
+        name: String,
+        command: String,
+        code: Option<i32>,
+    },
+    /// ERR-047, the text that is not an estimate
+    BadEstimate { text: String },
+    /// ERR-048, the todo with several estimates
+    MultipleEstimates { name: String },
+    /// ERR-049, the value of '--sort-by'
+    Order { value: String },
+    /// an error at a position of an input file
+    Located(Position, Box<TodoError>),
+}
+
+impl TodoError {
+    /// Returns the error code, such as "ERR-003".
+    pub fn code(&self) -> &'static str {
+        match self {
+            TodoError::SpaceInName { .. } => "ERR-001",
+            TodoError::Loop { .. } => "ERR-002",
+            TodoError::Missing { .. } => "ERR-003",
+            TodoError::Incomplete { .. } => "ERR-004",
+            TodoError::ScreenWidth { .. } => "ERR-005",
+            TodoError::Format { .. } => "ERR-006",
+            TodoError::NoRoot { .. } => "ERR-007",
+            TodoError::DuplicatedOwner { .. } => "ERR-008",
+            TodoError::ReservedName => "ERR-009",
+            TodoError::NoTodo => "ERR-010",
+            TodoError::CompletedDependency { .. } => "ERR-011",
+            TodoError::InconsistentDependency { .. } => "ERR-012",
+            TodoError::MissingHeading => "ERR-013",
+            TodoError::Duplicated { .. } => "ERR-014",
+            TodoError::Heading { .. } => "ERR-015",
+            TodoError::SelfDependency { .. } => "ERR-016",
+            TodoError::AtInName { .. } => "ERR-017",
+            TodoError::SlashInName { .. } => "ERR-018",
+            TodoError::Inotify(_) => "ERR-019",
+            TodoError::Reverse => "ERR-020",
+            TodoError::NoSuchOwner { .. } => "ERR-022",
+            TodoError::InputFormat { .. } => "ERR-023",
+            TodoError::Json { .. } => "ERR-024",
+            TodoError::AutoAdded { .. } => "ERR-025",
+            TodoError::NotMarkdown { .. } => "ERR-026",
+            TodoError::Ambiguous { .. } => "ERR-027",
+            TodoError::NotFile { .. } => "ERR-028",
+            TodoError::Arguments { .. } => "ERR-029",
+            TodoError::AlreadyDepends { .. } => "ERR-030",
+            TodoError::NotDependent { .. } => "ERR-031",
+            TodoError::NotFormatted { .. } => "ERR-032",
+            TodoError::Problems { .. } => "ERR-033",
+            TodoError::Io(_) => "ERR-034",
+            TodoError::Usage(_) => "ERR-035",
+            TodoError::BadDate { .. } => "ERR-036",
+            TodoError::MultipleDates { .. } => "ERR-037",
+            TodoError::BadPriority { .. } => "ERR-038",
+            TodoError::MultiplePriorities { .. } => "ERR-039",
+            TodoError::BadStatus { .. } => "ERR-040",
+            TodoError::MultipleStatuses { .. } => "ERR-041",
+            TodoError::Theme { .. } => "ERR-042",
+            TodoError::BadColor { .. } => "ERR-043",
+            TodoError::Toml { .. } => "ERR-044",
+            TodoError::NoRecipe { .. } => "ERR-045",
+            TodoError::RecipeFailed { .. } => "ERR-046",
+            TodoError::BadEstimate { .. } => "ERR-047",
+            TodoError::MultipleEstimates { .. } => "ERR-048",
+            TodoError::Order { .. } => "ERR-049",
+            TodoError::Located(_, err) => err.code(),
+        }
+    }
+
+    /// Returns the message, without the code and the position.
+    pub fn message(&self) -> String {
+        match self {
+            TodoError::SpaceInName { name, space } => format!(
+                "TODO name '{}' contains space character '{}'",
+                name, space
+            ),
+            TodoError::Loop { cycle } => {
+                format!("TODOs '{}' have a dependency loop", chain(cycle))
+            }
+            TodoError::Missing { name } => {
+                format!("TODO '{}' is missing in the markdown file", name)
+            }
+            TodoError::Incomplete { name, dependencies } => format!(
+                "TODO \"{}\" cannot be completed because its dependencies \
+                    {:?} are not completed or cancelled yet",
+                name, dependencies
+            ),
+            TodoError::ScreenWidth { width, needed } => format!(
+                "Screen width is {}, but this todotree needs at least {} \
+                    columns",
+                width, needed
+            ),
+            TodoError::Format { .. } => "Wrong parameter for -f".to_string(),
+            TodoError::NoRoot { cycles } => format!(
+                "Failed to find root node, as all todos are in dependency \
+                    loops '{}'",
+                cycles
+                    .iter()
+                    .map(|c| chain(c))
+                    .collect::<Vec<String>>()
+                    .join("', '")
+            ),
+            TodoError::DuplicatedOwner { owner, .. } => {
+                format!("Owner '{}' cannot be specified multiple times", owner)
+            }
+            TodoError::ReservedName => {
+                format!("'{}' is a reserved TODO name keyword", ROOT)
+            }
+            TodoError::NoTodo => {
+                "The markdown file does not have any TODO".to_string()
+            }
+            TodoError::CompletedDependency { name, parent } => format!(
+                "TODO '{}' has its own '# ' line, then it should not have \
+                    '~' in '{}'s dependencies list.",
+                name, parent
+            ),
+            TodoError::InconsistentDependency {
+                name,
+                dependency,
+                other,
+            } => format!(
+                "TODO '{}' has a dependency '~{}', but todo '{}' has a \
+                    dependency '{}'.",
+                name, dependency, other, dependency
+            ),
+            TodoError::MissingHeading => "Missing '# [TODO]' before '- @', \
+                '- :', or '-  %' in the todotree markdown file."
+                .to_string(),
+            TodoError::Duplicated { name } => {
+                format!("Duplicated todo name '{}'", name)
+            }
+            TodoError::Heading { line } => format!("TODO name '{}'", line),
+            TodoError::SelfDependency { name } => {
+                format!("TODO '{}' should not depend on itself", name)
+            }
+            TodoError::AtInName { name } => {
+                format!("TODO name '{}' should not contain '@'", name)
+            }
+            TodoError::SlashInName { name } => {
+                format!("TODO name '{}' should not end with '/'", name)
+            }
+            TodoError::Inotify(e) => format!("Inotify, {}", e),
+            TodoError::Reverse => {
+                "'--reverse' works with Term or Html only".to_string()
+            }
+            TodoError::NoSuchOwner { owner } => {
+                format!("No such owner '{}' in the markdown file", owner)
+            }
+            TodoError::InputFormat { .. } => {
+                "Wrong parameter for --input-format".to_string()
+            }
+            TodoError::Json { file, reason } => {
+                format!("'{}' is not a todotree JSON file, {}", file, reason)
+            }
+            TodoError::AutoAdded { name } => format!(
+                "TODO '{}' is auto-added from dependencies, it has no '# ' \
+                    line to edit",
+                name
+            ),
+            TodoError::NotMarkdown { file } => format!(
+                "'{}' is not a markdown file, only markdown can be edited",
+                file
+            ),
+            TodoError::Ambiguous { name } => format!(
+                "TODO '{}' is in several inputs, use '{}@FILE'",
+                name, name
+            ),
+            TodoError::NotFile { file } => format!("'{}' is not a file", file),
+            TodoError::Arguments { command } => {
+                format!("Wrong number of TODOs for command '{}'", command)
+            }
+            TodoError::AlreadyDepends { name, dependency } => {
+                format!("TODO '{}' already depends on '{}'", name, dependency)
+            }
+            TodoError::NotDependent { name, dependency } => {
+                format!("TODO '{}' does not depend on '{}'", name, dependency)
+            }
+            TodoError::NotFormatted { file } => {
+                format!("'{}' is not formatted, run 'todotree fmt'", file)
+            }
+            TodoError::Problems { errors } => {
+                format!("Found {} problem(s) in the inputs", errors.len())
+            }
+            TodoError::Io(e) => format!("I/O error: {}", e),
+            TodoError::Usage(msg) => msg.to_string(),
+            TodoError::BadDate { text } => {
+                format!("Wrong date '{}', it should be YYYY-MM-DD", text)
+            }
+            TodoError::MultipleDates { .. } => {
+                "Due or start date cannot be specified multiple times"
+                    .to_string()
+            }
+            TodoError::BadPriority { text } => format!(
+                "Wrong priority '{}', it should be P1 to P4, critical, high, \
+                medium or low",
+                text
+            ),
+            TodoError::MultiplePriorities { .. } => {
+                "Priority cannot be specified multiple times".to_string()
+            }
+            TodoError::BadStatus { text } => format!(
+                "Wrong status '{}', it should be in-progress, blocked, \
+                cancelled or waiting",
+                text
+            ),
+            TodoError::MultipleStatuses { name } => format!(
+                "Status of TODO '{}' cannot be specified multiple times, \
+                nor along with '~'",
+                name
+            ),
+            TodoError::Theme { value } => format!(
+                "Wrong theme '{}', it should be default, light, dark, \
+                colorblind or a '.toml' file",
+                value
+            ),
+            TodoError::BadColor { text } => format!(
+                "Wrong color '{}', it should be a color name, 0 to 255 or \
+                #RRGGBB",
+                text
+            ),
+            TodoError::Toml { file, reason } => {
+                format!("'{}' is not a todotree TOML file, {}", file, reason)
+            }
+            TodoError::NoRecipe { name } => format!(
+                "TODO '{}' has neither recipe nor dependencies, complete it \
+                by hand",
+                name
+            ),
+            TodoError::RecipeFailed {
+                name,
+                command,
+                code,
+            } => match code {
+                Some(code) => format!(
+                    "Recipe of TODO '{}' failed with exit code {}: {}",
+                    name, code, command
+                ),
+                None => format!(
+                    "Recipe of TODO '{}' was killed by a signal: {}",
+                    name, command
+                ),
+            },
+            TodoError::BadEstimate { text } => format!(
+                "Wrong estimate '{}', it should be numbers of weeks, days, \
+                hours or minutes, such as 3d or 1d 4h",
+                text
+            ),
+            TodoError::MultipleEstimates { .. } => {
+                "Estimate cannot be specified multiple times".to_string()
+            }
+            TodoError::Order { value } => format!(
+                "Wrong order '{}', it should be priority, due or unblocks",
+                value
+            ),
+            TodoError::Located(_, err) => err.message(),
+        }
+    }
+
+    /// Returns the position in an input file, if known.
+    pub fn position(&self) -> Option<&Position> {
+        match self {
+            TodoError::Located(pos, _) => Some(pos),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without its position, to match on its variant.
+    pub fn kind(&self) -> &TodoError {
+        match self {
+            TodoError::Located(_, err) => err,
+            _ => self,
+        }
+    }
+
+    /// Returns a JSON object with the code, message and position.
+    pub fn to_json(&self) -> String {
+        let (file, line, column) = match self.position() {
+            Some(pos) => (
+                json_quote(&pos.file),
+                pos.line.to_string(),
+                pos.column.to_string(),
+            ),
+            None => ("null".into(), "null".into(), "null".into()),
+        };
+        format!(
+            "{{\"code\": {}, \"message\": {}, \"file\": {}, \"line\": {}, \
+                \"column\": {}}}",
+            json_quote(self.code()),
+            json_quote(&self.message()),
+            file,
+            line,
+            column
+        )
+    }
+
+    /// Attaches a position to an error that does not have one yet.
+    pub(crate) fn at(self, position: &Position) -> Self {
+        match self {
+            TodoError::Io(_) | TodoError::Located(..) => self,
+            _ if position.line == 0 => self,
+            _ => TodoError::Located(position.clone(), Box::new(self)),
+        }
+    }
+}
+
+/// Returns a loop as 'a -> b -> a'.
+fn chain(cycle: &[String]) -> String {
+    let mut names: Vec<&str> = cycle.iter().map(|s| s.as_str()).collect();
+    names.extend(cycle.first().map(|s| s.as_str()));
+    names.join(" -> ")
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pos = match self {
+            TodoError::Located(pos, err) => {
+                write!(f, "{}:{}:{}: {}", pos.file, pos.line, pos.column, err)?;
+                pos
+            }
+            // as they were before the codes, for scripts matching them
+            TodoError::Io(_) | TodoError::Usage(_) => {
+                return write!(f, "{}", self.message());
+            }
+            _ => return write!(f, "{}: {}", self.code(), self.message()),
+        };
+        let gutter = " ".repeat(pos.line.to_string().len());
+        let before: String = pos.text.chars().take(pos.column - 1).collect();
+        writeln!(f)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", pos.line, pos.text)?;
+        write!(
+            f,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(before.width()),
+            "^".repeat(pos.width)
+        )
+    }
+}
+
+impl std::error::Error for TodoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TodoError::Io(e) | TodoError::Inotify(e) => Some(e),
+            TodoError::Located(_, err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<Fail> for TodoError {
+    fn from(err: Fail) -> Self {
+        TodoError::Usage(err.to_string())
+    }
+}
+
+impl From<io::Error> for TodoError {
+    fn from(err: io::Error) -> Self {
+        TodoError::Io(err)
+    }
+}
+
+impl From<ParseIntError> for TodoError {
+    fn from(err: ParseIntError) -> Self {
+        TodoError::Usage(err.to_string())
+    }
+}
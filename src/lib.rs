@@ -0,0 +1,315 @@
+//! Visualizes todos as a dependency tree instead of a flat list.
+//!
+//! ```no_run
+//! use todotree::{Format, Tree, TreeOptions};
+//!
+//! let options = TreeOptions::new()
+//!     .input("todotree.md")
+//!     .format(Format::Html)
+//!     .hide_done(true);
+//! let tree = Tree::new(&options).unwrap();
+//! print!("{}", tree);
+//! ```
+use std::fmt;
+use std::iter::Sum;
+use std::ops::Add;
+use std::str::FromStr;
+pub mod config;
+pub mod date;
+pub mod edit;
+pub mod error;
+pub mod run;
+pub mod theme;
+pub mod todo;
+pub mod tree;
+pub mod why;
+pub use config::Config;
+pub use date::Date;
+pub use error::{Position, TodoError};
+pub use theme::Theme;
+pub use todo::Todo;
+pub use tree::{Tree, TreeOptions};
+static ROOT: &str = "/";
+/// Version of the JSON output, see todotree.schema.json
+pub const JSON_VERSION: u32 = 2;
+static HTMLP: &str = "<p style='font-family: monospace; font-size: 16px; \
+    margin: 0px; line-height: 16px'>";
+
+/// Output format of a tree
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Format {
+    Html,
+    Json,
+    #[default]
+    Term,
+    Md,
+    Dot,
+    Mermaid,
+    /// the actionable todos, one per line
+    List,
+}
+impl FromStr for Format {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(Format::Html),
+            "json" => Ok(Format::Json),
+            "term" | "" => Ok(Format::Term),
+            "md" => Ok(Format::Md),
+            "dot" => Ok(Format::Dot),
+            "mermaid" => Ok(Format::Mermaid),
+            "list" => Ok(Format::List),
+            _ => Err(TodoError::Format {
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Format::Html => "html",
+                Format::Json => "json",
+                Format::Term => "term",
+                Format::Md => "md",
+                Format::Dot => "dot",
+                Format::Mermaid => "mermaid",
+                Format::List => "list",
+            }
+        )
+    }
+}
+
+/// Quotes and escapes a string for JSON.
+pub(crate) fn json_quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                quoted.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats strings as a JSON array on one line.
+pub(crate) fn json_array(items: &[String]) -> String {
+    let quoted: Vec<String> = items.iter().map(|s| json_quote(s)).collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+/// Format of an input file
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum InputFormat {
+    Md,
+    Json,
+}
+impl FromStr for InputFormat {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md" => Ok(InputFormat::Md),
+            "json" => Ok(InputFormat::Json),
+            _ => Err(TodoError::InputFormat {
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Status of a todo, marked in the input or computed from the dependencies
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Status {
+    Completed,
+    Pending,
+    Actionable,
+    /// someone started it
+    InProgress,
+    /// blocked by something outside the inputs
+    Blocked,
+    /// will not be done, which unblocks the todos depending on it
+    Cancelled,
+    /// deferred on purpose
+    Waiting,
+}
+impl Status {
+    /// Returns true if the todos depending on it are not blocked by it.
+    pub fn is_done(&self) -> bool {
+        matches!(self, Status::Completed | Status::Cancelled)
+    }
+
+    /// Returns the word of its '- * ' line, if it has one.
+    pub(crate) fn keyword(&self) -> Option<&'static str> {
+        match self {
+            Status::InProgress => Some("in-progress"),
+            Status::Blocked => Some("blocked"),
+            Status::Cancelled => Some("cancelled"),
+            Status::Waiting => Some("waiting"),
+            _ => None,
+        }
+    }
+}
+impl FromStr for Status {
+    type Err = TodoError;
+
+    /// Parses the word of a '- * ' line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "in-progress" | "started" => Ok(Status::InProgress),
+            "blocked" => Ok(Status::Blocked),
+            "cancelled" | "canceled" => Ok(Status::Cancelled),
+            "waiting" | "deferred" => Ok(Status::Waiting),
+            _ => Err(TodoError::BadStatus {
+                text: s.to_string(),
+            }),
+        }
+    }
+}
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Priority of a todo, P1 being the highest
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Priority {
+    P1,
+    P2,
+    P3,
+    P4,
+}
+impl FromStr for Priority {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "p1" | "critical" => Ok(Priority::P1),
+            "p2" | "high" => Ok(Priority::P2),
+            "p3" | "medium" => Ok(Priority::P3),
+            "p4" | "low" => Ok(Priority::P4),
+            _ => Err(TodoError::BadPriority {
+                text: s.to_string(),
+            }),
+        }
+    }
+}
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Order of the actionable todos of the 'list' format
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Order {
+    /// the highest effective priority first
+    Priority,
+    /// the earliest due date first
+    Due,
+    /// the todo unblocking the most todos first
+    Unblocks,
+}
+impl FromStr for Order {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "priority" => Ok(Order::Priority),
+            "due" => Ok(Order::Due),
+            "unblocks" => Ok(Order::Unblocks),
+            _ => Err(TodoError::Order {
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Units of an estimate with their minutes, a day being 8 hours and a week
+/// 5 days
+static UNITS: [(char, u64); 4] = [('w', 2400), ('d', 480), ('h', 60), ('m', 1)];
+
+/// Remaining effort of a todo, written as '3d', '1.5h' or '1w 2d'
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+pub struct Estimate {
+    minutes: u64,
+}
+impl Estimate {
+    /// Returns the effort in minutes.
+    pub fn minutes(&self) -> u64 {
+        self.minutes
+    }
+}
+impl Add for Estimate {
+    type Output = Estimate;
+
+    fn add(self, other: Estimate) -> Estimate {
+        Estimate {
+            minutes: self.minutes + other.minutes,
+        }
+    }
+}
+impl Sum for Estimate {
+    fn sum<I: Iterator<Item = Estimate>>(iter: I) -> Estimate {
+        iter.fold(Estimate::default(), |a, b| a + b)
+    }
+}
+impl FromStr for Estimate {
+    type Err = TodoError;
+
+    /// Parses numbers followed by w, d, h or m, such as '1d 4h'.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || TodoError::BadEstimate {
+            text: s.to_string(),
+        };
+        let (mut minutes, mut number) = (0.0, String::new());
+        for c in s.chars().filter(|c| !c.is_whitespace()) {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let unit = UNITS.iter().find(|(u, _)| *u == c).ok_or_else(bad)?;
+            let n: f64 = number.parse().map_err(|_| bad())?;
+            minutes += n * unit.1 as f64;
+            number.clear();
+        }
+        if !number.is_empty() || s.trim().is_empty() {
+            return Err(bad());
+        }
+        Ok(Estimate {
+            minutes: minutes.round() as u64,
+        })
+    }
+}
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut left = self.minutes;
+        let mut parts = vec![];
+        for (unit, minutes) in UNITS {
+            if left >= minutes {
+                parts.push(format!("{}{}", left / minutes, unit));
+                left %= minutes;
+            }
+        }
+        match parts.is_empty() {
+            true => write!(f, "0h"),
+            false => write!(f, "{}", parts.join(" ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -1,15 +1,58 @@
 use getopts::{Matches, Options};
-use std::collections::BTreeMap;
+use inotify::{Inotify, WatchMask};
 use std::env;
 use std::fs::OpenOptions;
 use std::io::{self, ErrorKind, Write};
+use std::process::ExitCode;
 use std::thread;
 use std::time::Duration;
-mod todotree;
-use inotify::{Inotify, WatchMask};
-use todotree::{TodoError, tree::Tree};
+use todotree::edit::{self, Mark};
+use todotree::{
+    Config, Format, JSON_VERSION, Theme, TodoError, Tree, TreeOptions,
+};
+use todotree::{run, why};
+
+/// Commands editing the input files, instead of showing the tree
+static COMMANDS: [&str; 11] = [
+    "done", "undone", "toggle", "add", "link", "unlink", "rename", "fmt",
+    "check", "run", "why",
+];
+
+fn main() -> ExitCode {
+    let mut json_errors = false;
+    match try_main(&mut json_errors) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            print_error(&e, json_errors);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-fn main() -> Result<(), TodoError> {
+/// Prints an error, with all the problems found by 'check'.
+fn print_error(err: &TodoError, json: bool) {
+    let errors = match err {
+        TodoError::Problems { errors } => errors.iter().collect(),
+        _ => vec![err],
+    };
+    if json {
+        let errors: Vec<String> = errors.iter().map(|e| e.to_json()).collect();
+        println!(
+            "{{\n  \"version\": {},\n  \"errors\": [\n    {}\n  ]\n}}",
+            JSON_VERSION,
+            errors.join(",\n    ")
+        );
+    } else {
+        if let TodoError::Problems { .. } = err {
+            for e in errors {
+                eprintln!("{}\n", e);
+            }
+        }
+        eprintln!("{}", err);
+    }
+}
+
+fn try_main(json_errors: &mut bool) -> Result<(), TodoError> {
     let args: Vec<String> = env::args().collect();
     let mut opts = Options::new();
     opts.optflag(
@@ -17,13 +60,29 @@ fn main() -> Result<(), TodoError> {
         "auto-add",
         "Auto-add missing TODO definitions from dependencies.",
     );
-    opts.optflag("C", "no-color", "Disable color output.");
+    opts.optflag(
+        "C",
+        "no-color",
+        "Disable color output, as does a non-empty NO_COLOR variable.",
+    );
     opts.optflag(
         "E",
         "example",
         "Create a sample todotree.md in the current directory.",
     );
+    opts.optflag(
+        "F",
+        "force",
+        "Let 'done' and 'undone' ignore the completion of dependencies.",
+    );
     opts.optflag("M", "hide-comment", "Hide comment column.");
+    opts.optopt(
+        "P",
+        "priority",
+        "Show only TODOs at or above PRIORITY, which is P1 to P4, \
+        critical, high, medium or low.",
+        "PRIORITY",
+    );
     opts.optflag("O", "hide-owner", "Hide owner column.");
     opts.optflag("R", "reverse", "Reverse tree order (root at bottom).");
     opts.optflag(
@@ -31,6 +90,42 @@ fn main() -> Result<(), TodoError> {
         "sort",
         "Sort tasks that are dependencies of the same task.",
     );
+    opts.optflag(
+        "",
+        "by-priority",
+        "Order tasks that are dependencies of the same task by priority.",
+    );
+    opts.optflag(
+        "",
+        "check",
+        "Let 'fmt' fail if an input is not formatted, instead of fixing it.",
+    );
+    opts.optflag(
+        "",
+        "critical-path",
+        "Highlight the longest chain of uncompleted TODOs from each TODO \
+        shown at the top, by estimate or else by count, and print the \
+        remaining effort.",
+    );
+    opts.optmulti(
+        "",
+        "comment",
+        "Comment line of the TODO created by 'add'. \
+                        May be specified multiple times.",
+        "TEXT",
+    );
+    opts.optmulti(
+        "",
+        "dep",
+        "Dependency of the TODO created by 'add'. \
+                        May be specified multiple times.",
+        "TODO",
+    );
+    opts.optflag(
+        "",
+        "dry-run",
+        "Let 'run' print the recipes it would run, instead of running them.",
+    );
     opts.optopt(
         "d",
         "depth",
@@ -44,18 +139,39 @@ fn main() -> Result<(), TodoError> {
                         May be specified multiple times.",
         "FILE",
     );
+    opts.optopt(
+        "",
+        "input-format",
+        "Input format: md | json (default: json for '*.json', otherwise md).",
+        "FORMAT",
+    );
     opts.optopt(
         "f",
         "format",
-        "Output format: term | md | html | json (default: term).",
+        "Output format: term | md | html | json | dot | mermaid | list \
+        (default: term). json ignores the options hiding TODOs or columns.",
         "FORMAT",
     );
+    opts.optopt(
+        "j",
+        "jobs",
+        "Let 'run' run up to N recipes at once (default: 1).",
+        "N",
+    );
     opts.optmulti(
         "o",
         "owner",
-        "Show only TODOs owned by OWNER. May be specified multiple times.",
+        "Show only TODOs owned by OWNER or by a member of team OWNER, \
+                        or set an owner for 'add'. \
+                        May be specified multiple times.",
         "OWNER",
     );
+    opts.optflag(
+        "n",
+        "next",
+        "List the actionable TODOs with the TODOs at the top they unblock, \
+        as '-f list' does.",
+    );
     opts.optflag("q", "hide-done", "Hide completed TODOs.");
     opts.optflag("r", "refresh", "Auto-refresh when input file changes.");
     opts.optopt(
@@ -64,9 +180,66 @@ fn main() -> Result<(), TodoError> {
         "Join multi-line comments with STRING (default: \"\\n\").",
         "STR",
     );
+    opts.optopt(
+        "",
+        "sort-by",
+        "Order the actionable TODOs of '-f list' by priority, due date or \
+        number of TODOs they unblock: priority | due | unblocks.",
+        "KEY",
+    );
+    opts.optmulti(
+        "t",
+        "tag",
+        "Show only TODOs tagged with TAG. May be specified multiple times.",
+        "TAG",
+    );
+    opts.optmulti(
+        "T",
+        "exclude-tag",
+        "Hide TODOs tagged with TAG. May be specified multiple times.",
+        "TAG",
+    );
+    opts.optopt(
+        "",
+        "theme",
+        "Color theme: default | light | dark | colorblind, or a '.toml' \
+        FILE defining one (default: default).",
+        "THEME",
+    );
+    opts.optopt(
+        "",
+        "upstream",
+        "Show the TODOs depending on TODO, directly or not, as a tree \
+        below it, instead of its dependencies.",
+        "TODO",
+    );
+    opts.optopt(
+        "",
+        "today",
+        "Compare due and start dates with DATE (YYYY-MM-DD) instead of today.",
+        "DATE",
+    );
+    opts.optflag(
+        "",
+        "no-config",
+        "Ignore the configuration files, see Configuration above.",
+    );
+    for key in Config::flags() {
+        opts.optflag(
+            "",
+            &negation(key),
+            &format!("Turn off '{}' set in the configuration files.", key),
+        );
+    }
+    opts.optflag(
+        "",
+        "json-errors",
+        "Print errors as JSON to stdout, e.g. along with '-f json'.",
+    );
     opts.optflag("h", "help", "Show this help and exit.");
     opts.optflag("", "version", "Show version information and exit.");
     let mut matches = opts.parse(&args[1..])?;
+    *json_errors = matches.opt_present("json-errors");
     if matches.opt_present("version") {
         return print_version();
     }
@@ -93,38 +266,42 @@ fn main() -> Result<(), TodoError> {
         file.write_all(content.as_bytes())?;
         println!("Created todotree.md");
     }
+    let config = match matches.opt_present("no-config") {
+        true => Config::default(),
+        false => Config::load()?,
+    };
     let mut inputs = matches.opt_strs("input");
+    if inputs.is_empty() {
+        inputs = config.strings("input");
+    }
     if inputs.is_empty() {
         inputs = vec!["todotree.md".to_string()];
     }
-    let mut owners: BTreeMap<String, bool> = matches
-        .opt_strs("owner")
-        .into_iter()
-        .map(|s| (s.to_string(), false))
-        .collect::<BTreeMap<String, bool>>();
-    if matches.opt_present("sort") {
+    if let Some(cmd) = matches.free.first()
+        && COMMANDS.contains(&cmd.as_str())
+        && !edit::is_todo(&tree_options(&matches, &config, &inputs)?, cmd)
+    {
+        return run_command(&matches, &config, &inputs);
+    }
+    if flag(&matches, &config, "sort") {
         matches.free.sort();
     }
-    let targets = if matches.free.is_empty() {
-        &vec![]
-    } else {
-        &matches.free
-    };
     loop {
-        let rtn = print_tree(&matches, &inputs, &mut owners, targets);
+        let rtn = print_tree(&matches, &config, &inputs);
         if !matches.opt_present("refresh") {
             return rtn;
         }
-        if matches.opt_str("format").is_some_and(|s| s != "term") {
+        let format = matches.opt_str("format").or(config.string("format"));
+        if format.is_some_and(|s| s != "term") {
             return rtn;
         }
-        if rtn.is_err() {
-            if let Err(TodoError::Io(ref err)) = rtn
+        if let Err(ref e) = rtn {
+            if let TodoError::Io(err) = e
                 && err.kind() == io::ErrorKind::NotFound
             {
                 return rtn;
             }
-            println!("{:?}", rtn);
+            println!("{}", e);
         }
         let mut inotify = Inotify::init()?;
         for mdfile in &inputs {
@@ -135,10 +312,7 @@ fn main() -> Result<(), TodoError> {
                 if e.kind() == ErrorKind::NotFound {
                     thread::sleep(Duration::from_secs(1));
                 } else {
-                    return Err(TodoError::Input(format!(
-                        "ERR-019: Inotify, {}",
-                        e
-                    )));
+                    return Err(TodoError::Inotify(e));
                 }
             }
         }
@@ -149,41 +323,204 @@ fn main() -> Result<(), TodoError> {
 
 fn print_tree(
     matches: &Matches,
-    inputs: &Vec<String>,
-    owners: &mut BTreeMap<String, bool>,
-    targets: &[String],
+    config: &Config,
+    inputs: &[String],
 ) -> Result<(), TodoError> {
     if matches.opt_present("refresh") {
         print!("\x1B[2J\x1B[1;1H");
         io::stdout().flush()?;
     }
-    let format = matches.opt_str("format").unwrap_or_default();
-    let depth: i32 = match matches.opt_str("depth") {
+    let tree = Tree::new(&tree_options(matches, config, inputs)?)?;
+    print!("{}", tree);
+    Ok(())
+}
+
+fn run_command(
+    matches: &Matches,
+    config: &Config,
+    inputs: &[String],
+) -> Result<(), TodoError> {
+    let options = tree_options(matches, config, inputs)?;
+    let (cmd, args) = (matches.free[0].as_str(), &matches.free[1..]);
+    let expected = match cmd {
+        "add" | "why" => args.len() == 1,
+        "link" | "unlink" | "rename" => args.len() == 2,
+        "fmt" | "check" => args.is_empty(),
+        _ => !args.is_empty(),
+    };
+    if !expected {
+        return Err(TodoError::Arguments {
+            command: cmd.to_string(),
+        });
+    }
+    match cmd {
+        "add" => {
+            edit::add(
+                &options,
+                &args[0],
+                &matches.opt_strs("owner"),
+                &matches.opt_strs("dep"),
+                &matches.opt_strs("comment"),
+            )?;
+            println!("{}: added", args[0]);
+        }
+        "link" => {
+            edit::link(&options, &args[0], &args[1])?;
+            println!("{}: depends on {}", args[0], args[1]);
+        }
+        "unlink" => {
+            edit::unlink(&options, &args[0], &args[1])?;
+            println!("{}: no longer depends on {}", args[0], args[1]);
+        }
+        "fmt" => {
+            let check = matches.opt_present("check");
+            let changed = edit::fmt(&options, check)?;
+            if !check {
+                for mdfile in changed {
+                    println!("{}: formatted", mdfile);
+                }
+                return Ok(());
+            }
+            let mut errors: Vec<TodoError> = changed
+                .into_iter()
+                .map(|file| TodoError::NotFormatted { file })
+                .collect();
+            match errors.len() {
+                0 => {}
+                1 => return Err(errors.remove(0)),
+                _ => return Err(TodoError::Problems { errors }),
+            }
+        }
+        "check" => {
+            let errors = Tree::check(&options);
+            if !errors.is_empty() {
+                return Err(TodoError::Problems { errors });
+            }
+        }
+        "rename" => {
+            edit::rename(&options, &args[0], &args[1])?;
+            println!("{}: renamed to {}", args[0], args[1]);
+        }
+        "why" => print!("{}", why::why(&options, &args[0])?),
+        "run" => {
+            let steps = run::plan(&options, args)?;
+            if matches.opt_present("dry-run") {
+                for step in &steps {
+                    println!("{}", step.name());
+                    for command in step.recipe() {
+                        println!("    $ {}", command);
+                    }
+                }
+                return Ok(());
+            }
+            let jobs = match matches.opt_str("jobs") {
+                Some(x) => x.parse()?,
+                None => 1,
+            };
+            run::run(&options, &steps, jobs, &mut io::stdout())?;
+        }
+        _ => {
+            let how = match cmd {
+                "done" => Mark::Done,
+                "undone" => Mark::Undone,
+                _ => Mark::Toggle,
+            };
+            for name in args {
+                let force = matches.opt_present("force");
+                let done = edit::mark(&options, name, how, force)?;
+                println!("{}: {}", name, if done { "done" } else { "undone" });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Names the option turning off a flag of the configuration files.
+fn negation(key: &str) -> String {
+    match key.strip_prefix("no-") {
+        Some(key) => key.to_string(),
+        None => format!("no-{}", key),
+    }
+}
+
+/// Tells whether a flag is set, on the command line or else in the
+/// configuration files.
+fn flag(matches: &Matches, config: &Config, key: &str) -> bool {
+    matches.opt_present(key)
+        || config.flag(key) && !matches.opt_present(&negation(key))
+}
+
+/// Returns the options of the command line, or else of the configuration.
+fn tree_options(
+    matches: &Matches,
+    config: &Config,
+    inputs: &[String],
+) -> Result<TreeOptions, TodoError> {
+    let flag = |name| flag(matches, config, name);
+    let string = |name| matches.opt_str(name).or_else(|| config.string(name));
+    let strings = |name| match matches.opt_strs(name) {
+        v if v.is_empty() => config.strings(name),
+        v => v,
+    };
+    let format: Format = match matches.opt_str("format") {
+        Some(x) => x.parse()?,
+        None if flag("next") => Format::List,
+        None => config.string("format").unwrap_or_default().parse()?,
+    };
+    let depth: i32 = match string("depth") {
         Some(x) => x.parse()?,
         None => 0,
     };
-    let separator = match matches.opt_str("separator") {
+    let separator = match string("separator") {
         Some(x) => x,
         None => "\n".to_string(),
     };
-    let tree = Tree::new(
-        inputs,
-        owners,
-        targets,
-        0,
-        &format,
-        matches.opt_present("hide-done"),
-        depth,
-        &separator,
-        matches.opt_present("no-color"),
-        matches.opt_present("auto-add"),
-        matches.opt_present("hide-comment"),
-        matches.opt_present("hide-owner"),
-        matches.opt_present("reverse"),
-        matches.opt_present("sort"),
-    )?;
-    print!("{}", tree);
-    Ok(())
+    let mut options = TreeOptions::new()
+        .inputs(inputs.to_vec())
+        .owners(strings("owner"))
+        .targets(matches.free.clone())
+        .format(format)
+        .hide_done(flag("hide-done"))
+        .depth(depth)
+        .separator(separator)
+        .no_color(
+            flag("no-color")
+                || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        )
+        .truecolor(
+            env::var("COLORTERM")
+                .is_ok_and(|v| v == "truecolor" || v == "24bit"),
+        )
+        .auto_add(flag("auto-add"))
+        .hide_comment(flag("hide-comment"))
+        .hide_owner(flag("hide-owner"))
+        .reverse(flag("reverse"))
+        .sort(flag("sort"))
+        .by_priority(flag("by-priority"))
+        .critical_path(flag("critical-path"))
+        .tags(strings("tag"))
+        .exclude_tags(strings("exclude-tag"));
+    if let Some(x) = matches.opt_str("input-format") {
+        options = options.input_format(x.parse()?);
+    }
+    if let Some(x) = string("sort-by") {
+        options = options.order(x.parse()?);
+    }
+    if let Some(x) = string("priority") {
+        options = options.priority(x.parse()?);
+    }
+    if let Some(x) = matches.opt_str("theme") {
+        options = options.theme(Theme::named_or_file(&x)?);
+    } else if let Some(theme) = config.theme() {
+        options = options.theme(theme.clone());
+    }
+    if let Some(x) = matches.opt_str("upstream") {
+        options = options.upstream(x);
+    }
+    if let Some(x) = matches.opt_str("today") {
+        options = options.today(x.parse()?);
+    }
+    Ok(options)
 }
 
 fn print_version() -> Result<(), TodoError> {
@@ -203,11 +540,26 @@ fn print_usage(opts: &Options) -> Result<(), TodoError> {
         opts.usage(
             "\
 Usage: todotree [options] [TODO]...
+       todotree [options] done|undone|toggle TODO...
+       todotree [options] add TODO
+       todotree [options] link|unlink TODO DEPENDENCY
+       todotree [options] rename TODO NEW_NAME
+       todotree [options] fmt [--check]
+       todotree [options] check
+       todotree [options] run [--dry-run] [-j N] TODO...
+       todotree [options] why TODO
 
 Description:
 Visualizes tasks as a dependency tree instead of a flat list.
 Highlights dependencies, color-codes task status, and uses a Markdown
 format as input.
+A first TODO named like a command, such as 'check', is shown as a TODO.
+
+Configuration:
+Default options are read from $XDG_CONFIG_HOME/todotree/config.toml, then
+from the first .todotree.toml found from the current directory upwards.
+The keys are the long options, such as 'input', 'sort' or 'theme', and
+the command line overrides them, e.g. '--no-sort' turns off 'sort = true'.
 
 Repository: https://github.com/daimh/todotree
 
@@ -220,11 +572,20 @@ Examples:
     todotree -i todotree.md
     todotree -i todotree.md lawn
     todotree -A -i minimalist.md
+    todotree -i todotree.md done dinner
+    todotree -i todotree.md undone garden
+    todotree -i todotree.md add tickets -o Mom --dep dinner --comment online
+    todotree -i todotree.md link movie tickets
+    todotree -i todotree.md rename lawn yard
+    todotree -i todotree.md fmt --check
+    todotree -i recipes.md run --dry-run release
+    todotree -i todotree.md why movie
+    todotree -i estimates.md --critical-path
+    todotree -i todotree.md --next --sort-by unblocks -o Mom
+    todotree -A -i shared.md --upstream api
+    todotree -i todotree.md check
 "
         )
     );
     Ok(())
 }
-
-#[cfg(test)]
-mod tests;
@@ -0,0 +1,174 @@
+//! Runs the recipes of todos in dependency order, as make does.
+use super::{
+    TodoError,
+    edit::{self, Mark},
+    todo::Todo,
+    tree::TreeOptions,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+
+/// A todo to run
+#[derive(Clone, Debug)]
+pub struct Step {
+    name: String,
+    /// the '- $ ' lines
+    recipe: Vec<String>,
+    /// the directory of its markdown file, where the recipe runs
+    dir: PathBuf,
+    /// the steps it waits for, by index in the plan
+    waits: Vec<usize>,
+}
+
+impl Step {
+    /// Returns the todo name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the shell commands, none if it only waits for others.
+    pub fn recipe(&self) -> &[String] {
+        &self.recipe
+    }
+}
+
+/// Returns the todos to complete for the targets, each one after the todos it
+/// depends on.
+///
+/// A todo without recipe is completed once its dependencies are, unless it
+/// has none, which has to be completed by hand.
+pub fn plan(
+    opts: &TreeOptions,
+    targets: &[String],
+) -> Result<Vec<Step>, TodoError> {
+    fn visit(
+        todos: &BTreeMap<String, Rc<RefCell<Todo>>>,
+        name: &str,
+        index: &mut BTreeMap<String, usize>,
+        steps: &mut Vec<Step>,
+    ) -> Result<Option<usize>, TodoError> {
+        if let Some(idx) = index.get(name) {
+            return Ok(Some(*idx));
+        }
+        let todo = edit::find(todos, name)?.borrow();
+        if todo.status.is_done() {
+            return Ok(None);
+        }
+        if todo.recipe.is_empty() && todo.dependencies.is_empty() {
+            return Err(TodoError::NoRecipe {
+                name: todo.name.clone(),
+            }
+            .at(&todo.position));
+        }
+        let mut waits = Vec::new();
+        for dep in &todo.dependencies {
+            if let Some(idx) =
+                visit(todos, &dep.replace("~", ""), index, steps)?
+            {
+                waits.push(idx);
+            }
+        }
+        let dir = match Path::new(&todo.file).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        steps.push(Step {
+            name: todo.name.clone(),
+            recipe: todo.recipe.clone(),
+            dir,
+            waits,
+        });
+        index.insert(todo.name.clone(), steps.len() - 1);
+        Ok(Some(steps.len() - 1))
+    }
+    let tree = edit::load(opts)?;
+    let todos = edit::by_name(&tree);
+    let mut index = BTreeMap::new();
+    let mut steps = Vec::new();
+    for target in targets {
+        let name = edit::find(&todos, target)?.borrow().name.clone();
+        visit(&todos, &name, &mut index, &mut steps)?;
+    }
+    Ok(steps)
+}
+
+/// Runs the recipes of a plan, up to `jobs` of them at once, and marks each
+/// todo completed when its recipe succeeds.
+///
+/// No step starts after a failure, and the error of the first failure is
+/// returned once the running ones are finished.
+pub fn run(
+    opts: &TreeOptions,
+    steps: &[Step],
+    jobs: usize,
+    out: &mut dyn Write,
+) -> Result<(), TodoError> {
+    let (tx, rx) = mpsc::channel();
+    let mut started = vec![false; steps.len()];
+    let mut done = vec![false; steps.len()];
+    let mut running = 0;
+    let mut failure = None;
+    loop {
+        for (idx, step) in steps.iter().enumerate() {
+            if failure.is_some() || running >= jobs.max(1) {
+                break;
+            }
+            if started[idx] || !step.waits.iter().all(|w| done[*w]) {
+                continue;
+            }
+            started[idx] = true;
+            running += 1;
+            for command in &step.recipe {
+                writeln!(out, "{}: $ {}", step.name, command)?;
+            }
+            let (tx, step) = (tx.clone(), step.clone());
+            thread::spawn(move || {
+                let _ = tx.send((idx, execute(&step)));
+            });
+        }
+        if running == 0 {
+            break;
+        }
+        let (idx, result) = rx.recv().expect("a step thread has panicked");
+        running -= 1;
+        let name = &steps[idx].name;
+        match result.and_then(|_| edit::mark(opts, name, Mark::Done, false)) {
+            Ok(_) => {
+                done[idx] = true;
+                writeln!(out, "{}: done", name)?;
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Runs each command of a recipe in its own shell, until one fails.
+fn execute(step: &Step) -> Result<(), TodoError> {
+    for command in &step.recipe {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&step.dir)
+            .status()?;
+        if !status.success() {
+            return Err(TodoError::RecipeFailed {
+                name: step.name.clone(),
+                command: command.clone(),
+                code: status.code(),
+            });
+        }
+    }
+    Ok(())
+}
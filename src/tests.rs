@@ -1,7 +1,19 @@
 use super::*;
 use std::fmt::Write;
 use std::fs::{read_dir, read_to_string};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Creates a directory of its own for a test, so that tests running at once
+/// do not share files.
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "todotree-{}-{}",
+        test,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
 
 #[test]
 fn examples() {
@@ -10,9 +22,16 @@ fn examples() {
         if !md.ends_with(".md") {
             continue;
         }
-        let inputs = vec![md.clone()];
-        let f4 = vec!["term", "json", "html", "md"];
-        let f2 = vec!["term", "html"];
+        let f4 = vec![
+            Format::Term,
+            Format::Json,
+            Format::Html,
+            Format::Md,
+            Format::Dot,
+            Format::Mermaid,
+            Format::List,
+        ];
+        let f2 = vec![Format::Term, Format::Html];
         for idx in 0..5 {
             let (hide, depth, outdir, reverse, formats) = match idx {
                 0 => (false, 0, "examples/output/", false, &f4),
@@ -24,20 +43,16 @@ fn examples() {
             println!("Index: {}\t{}", md, outdir);
             for format in formats {
                 let result = Tree::new(
-                    &inputs,
-                    &mut BTreeMap::<String, bool>::new(),
-                    &mut Vec::<String>::new(),
-                    80,
-                    format,
-                    hide,
-                    depth,
-                    "\n",
-                    false,
-                    true,
-                    false,
-                    false,
-                    reverse,
-                    true,
+                    &TreeOptions::new()
+                        .input(&md)
+                        .term_width(80)
+                        .format(*format)
+                        .hide_done(hide)
+                        .depth(depth)
+                        .auto_add(true)
+                        .reverse(reverse)
+                        .sort(true)
+                        .today(Date::new(2026, 10, 17).unwrap()),
                 );
                 let tree = match result {
                     Ok(t) => t,
@@ -52,7 +67,7 @@ fn examples() {
                 }
                 let basefile = md[0..md.len() - 3].replace("examples/", outdir)
                     + "."
-                    + format;
+                    + &format.to_string();
                 let standard = match read_to_string(&basefile) {
                     Ok(s) => s,
                     Err(e) => {
@@ -77,48 +92,32 @@ fn examples() {
 fn errors() {
     for path in read_dir("tests/errors/").unwrap() {
         let md = path.unwrap().path().display().to_string();
-        if !md.ends_with(".md") || !md.starts_with("tests/errors/ERR") {
+        if !(md.ends_with(".md") || md.ends_with(".json"))
+            || !md.starts_with("tests/errors/ERR")
+        {
             continue;
         }
         if md.len() < 20 {
             panic!("ERR-906: md: {}", md);
         }
         println!("Input: {}", md);
-        let inputs = vec![md.clone()];
-        let options = md[17..].replace(".md", "");
-        let mut auto_add = false;
-        let mut owners = BTreeMap::<String, bool>::new();
+        let options = md[17..].replace(".md", "").replace(".json", "");
+        let mut opts =
+            TreeOptions::new().input(&md).term_width(80).separator(" ");
         for opt in options.split("-") {
             if opt.starts_with('A') {
-                auto_add = true;
-            } else if opt.starts_with('o') {
-                owners = opt[1..]
-                    .split(",")
-                    .map(|s| (s.to_string(), false))
-                    .collect();
+                opts = opts.auto_add(true);
+            } else if let Some(owners) = opt.strip_prefix('o') {
+                opts = opts
+                    .owners(owners.split(",").map(|s| s.to_string()).collect());
             }
         }
-        match Tree::new(
-            &inputs,
-            &mut owners,
-            &vec![],
-            80,
-            "term",
-            false,
-            0,
-            " ",
-            false,
-            auto_add,
-            false,
-            false,
-            false,
-            false,
-        ) {
+        match Tree::new(&opts) {
             Err(e) => {
                 let err_code =
                     &Path::new(&md).file_stem().unwrap().to_string_lossy()
                         [0..7];
-                assert!(e.to_string().starts_with(err_code), "{}, {}", md, e)
+                assert_eq!(e.code(), err_code, "{}, {}", md, e)
             }
             _ => {
                 panic!("ERR-905: md: {}", md);
@@ -128,26 +127,95 @@ fn errors() {
 }
 
 #[test]
-fn multi() {
-    let inputs = vec![
-        String::from("tests/multi-input/1.md"),
-        String::from("tests/multi-input/2.md"),
+fn positions() {
+    let err = Tree::new(&TreeOptions::new().input("tests/errors/ERR-003.md"))
+        .err()
+        .expect("ERR-924: ERR-003.md is valid");
+    let expected = "\
+tests/errors/ERR-003.md:2:6: ERR-003: TODO 'garden' is missing in the markdown file
+  |
+2 | - : ~garden dinner lawn
+  |      ^^^^^^";
+    assert_eq!(err.to_string(), expected);
+    let err = Tree::new(&TreeOptions::new().input("tests/errors/ERR-014.md"))
+        .err()
+        .expect("ERR-925: ERR-014.md is valid");
+    let pos = err.position().expect("ERR-926: ERR-014 has no position");
+    assert_eq!((pos.line(), pos.column()), (6, 3), "{}", pos.text());
+    match err.kind() {
+        TodoError::Duplicated { name } => assert_eq!(name, "duplicated"),
+        _ => panic!("ERR-928: {}", err),
+    }
+    let json = err.to_json();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["code"], "ERR-014", "{}", json);
+    assert_eq!(value["line"], 6, "{}", json);
+    let err = Tree::new(&TreeOptions::new().input("tests/errors/ERR-017.json"))
+        .err()
+        .expect("ERR-959: ERR-017.json is valid");
+    let pos = err.position().expect("ERR-960: ERR-017 has no position");
+    assert_eq!((pos.line(), pos.column()), (15, 19), "{}", pos.text());
+    let err = TodoError::from(std::io::Error::other("gone"));
+    assert_eq!(
+        (err.code(), err.to_string().as_str()),
+        ("ERR-034", "I/O error: gone")
+    );
+}
+
+#[test]
+fn check() {
+    let codes: Vec<String> =
+        Tree::check(&TreeOptions::new().input("tests/check/in.md"))
+            .iter()
+            .map(|e| e.code().to_string())
+            .collect();
+    let expected = [
+        "ERR-008", "ERR-011", "ERR-003", "ERR-004", "ERR-002", "ERR-016",
+        "ERR-001", "ERR-014",
+    ];
+    assert_eq!(codes, expected);
+    let opts = TreeOptions::new().input("examples/todotree.md");
+    assert!(Tree::check(&opts).is_empty(), "ERR-927: todotree.md");
+}
+
+#[test]
+fn loops() {
+    let opts = TreeOptions::new().input("tests/check/loops.md");
+    let errors = Tree::check(&opts);
+    let found: Vec<(Vec<&str>, usize)> = errors
+        .iter()
+        .map(|e| match e.kind() {
+            TodoError::Loop { cycle } => (
+                cycle.iter().map(|s| s.as_str()).collect(),
+                e.position().map_or(0, |p| p.line()),
+            ),
+            _ => panic!("ERR-929: {}", e),
+        })
+        .collect();
+    let expected = vec![
+        (vec!["a", "b"], 8),
+        (vec!["a", "c", "b"], 8),
+        (vec!["d", "e"], 17),
     ];
+    assert_eq!(found, expected);
+    let err = Tree::new(&TreeOptions::new().input("tests/errors/ERR-007.md"))
+        .err()
+        .expect("ERR-930: ERR-007.md is valid");
+    assert!(
+        err.to_string().ends_with("loops 'a -> b -> c -> a'"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn multi() {
     let result = Tree::new(
-        &inputs,
-        &mut BTreeMap::<String, bool>::new(),
-        &mut Vec::<String>::new(),
-        80,
-        "term",
-        false,
-        0,
-        "\n",
-        false,
-        true,
-        false,
-        false,
-        false,
-        false,
+        &TreeOptions::new()
+            .input("tests/multi-input/1.md")
+            .input("tests/multi-input/2.md")
+            .term_width(80)
+            .auto_add(true),
     );
     let tree = match result {
         Ok(t) => t,
@@ -168,3 +236,558 @@ fn multi() {
     };
     assert!(standard == output, "ERR-910: multi-input");
 }
+
+/// Returns the todos shown in a tree, in tree order, as described.
+fn shown(opts: &TreeOptions, describe: fn(&Todo) -> String) -> String {
+    let tree = match Tree::new(opts) {
+        Ok(t) => t,
+        Err(e) => panic!("ERR-931: {:?}, {}", opts.inputs, e),
+    };
+    let mut shown = vec![];
+    let mut stack: Vec<_> =
+        tree.root().children().iter().rev().cloned().collect();
+    while let Some(todo) = stack.pop() {
+        let todo = todo.borrow();
+        shown.push(describe(&todo));
+        stack.extend(todo.children().iter().rev().cloned());
+    }
+    shown.join(" ")
+}
+
+#[test]
+fn priority() {
+    let opts = TreeOptions::new().input("examples/priority.md");
+    let describe =
+        |t: &Todo| format!("{}:{:?}", t.name(), t.effective_priority());
+    assert_eq!(
+        shown(&opts.clone().by_priority(true), describe),
+        "hotfix:Some(P1) audit:Some(P1) release:Some(P2) \
+        changelog:Some(P2) translations:Some(P2) glossary:Some(P2) \
+        cleanup:Some(P4)"
+    );
+    assert_eq!(
+        shown(&opts.priority(Priority::P1), describe),
+        "release:Some(P2) audit:Some(P1) hotfix:Some(P1)"
+    );
+}
+
+#[test]
+fn tags() {
+    let opts = TreeOptions::new().input("examples/tags.md");
+    let describe = |t: &Todo| t.name().to_string();
+    assert_eq!(
+        shown(&opts.clone().tags(vec!["backend".to_string()]), describe),
+        "website login search index"
+    );
+    assert_eq!(
+        shown(&opts.exclude_tags(vec!["infra".to_string()]), describe),
+        "website login search"
+    );
+}
+
+#[test]
+fn teams() {
+    let opts = TreeOptions::new().input("examples/teams.md");
+    let describe = |t: &Todo| t.name().to_string();
+    let owners = |owners: &[&str]| {
+        opts.clone()
+            .owners(owners.iter().map(|o| o.to_string()).collect())
+    };
+    assert_eq!(shown(&owners(&["backend"]), describe), "launch api schema");
+    assert_eq!(
+        shown(&owners(&["@web"]), describe),
+        "launch api schema site"
+    );
+    assert_eq!(shown(&owners(&["Dan"]), describe), "launch site assets");
+}
+
+#[test]
+fn statuses() {
+    let opts = TreeOptions::new().input("examples/statuses.md");
+    let describe = |t: &Todo| format!("{}:{}", t.name(), t.status());
+    assert_eq!(
+        shown(&opts.hide_done(true), describe),
+        "move:Pending pack:InProgress lease:Blocked deposit:Waiting"
+    );
+}
+
+#[test]
+fn critical_path() {
+    for (text, minutes) in [("3d", 1440), ("1.5h", 90), ("1w 2d 30m", 3390)] {
+        let estimate: Estimate = text.parse().unwrap();
+        assert_eq!(estimate.minutes(), minutes, "ERR-941: {}", text);
+    }
+    assert_eq!("1.5d".parse::<Estimate>().unwrap().to_string(), "1d 4h");
+    for text in ["", "3", "3x", "d"] {
+        match text.parse::<Estimate>() {
+            Err(e) => assert_eq!(e.code(), "ERR-047", "{}", e),
+            _ => panic!("ERR-942: estimate '{}' is accepted", text),
+        }
+    }
+    let opts = TreeOptions::new()
+        .input("examples/estimates.md")
+        .critical_path(true);
+    let describe = |t: &Todo| format!("{}:{}", t.name(), t.is_critical());
+    assert_eq!(
+        shown(&opts.clone().hide_done(true), describe),
+        "launch:true api:false schema:false web:true design:true docs:false"
+    );
+    let tree = Tree::new(&opts).unwrap();
+    let paths: Vec<String> = tree
+        .critical_paths()
+        .iter()
+        .map(|c| format!("{:?} {}", c.todos(), c))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "[\"launch\", \"web\", \"design\"] 1w 2d, 3 todos, 1 without estimate"
+        ]
+    );
+    assert_eq!(tree.remaining().estimate().to_string(), "2w 1d 4h");
+    assert_eq!(tree.remaining().unestimated(), 1);
+    let target = vec!["api".to_string(), "docs".to_string()];
+    let tree = Tree::new(&opts.targets(target)).unwrap();
+    let paths: Vec<String> = tree
+        .critical_paths()
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    assert_eq!(paths, ["4d, 2 todos", "4h, 1 todo"]);
+}
+
+#[test]
+fn next() {
+    let opts = TreeOptions::new().input("examples/priority.md");
+    let actions = |opts: &TreeOptions| {
+        let tree = match Tree::new(opts) {
+            Ok(t) => t,
+            Err(e) => panic!("ERR-943: {:?}, {}", opts.inputs, e),
+        };
+        let actions: Vec<String> = tree
+            .actions()
+            .iter()
+            .map(|a| {
+                let goals = a.goals().join(",");
+                format!("{}:{}:{}", a.todo().name(), a.unblocks(), goals)
+            })
+            .collect();
+        actions.join(" ")
+    };
+    assert_eq!(
+        actions(&opts.clone().order(Order::Unblocks)),
+        "glossary:3:release,cleanup audit:2:release,hotfix \
+        changelog:1:release"
+    );
+    assert_eq!(
+        actions(&opts.clone().order(Order::Priority)),
+        "audit:2:release,hotfix changelog:1:release \
+        glossary:3:release,cleanup"
+    );
+    let target = vec!["hotfix".to_string()];
+    assert_eq!(actions(&opts.targets(target)), "audit:1:hotfix");
+    let opts = TreeOptions::new().input("examples/teams.md");
+    let owners = vec!["Dan".to_string()];
+    assert_eq!(actions(&opts.owners(owners)), "assets:2:launch");
+}
+
+#[test]
+fn upstream() {
+    let opts = TreeOptions::new()
+        .input("examples/shared.md")
+        .auto_add(true);
+    let describe = |t: &Todo| format!("{}:{}", t.name(), t.status());
+    assert_eq!(
+        shown(&opts.clone().upstream("api"), describe),
+        "api:Actionable docs:Pending release:Pending binary:Pending"
+    );
+    assert_eq!(
+        shown(&opts.clone().upstream("docs").depth(1), describe),
+        "docs/:Pending"
+    );
+    let tree = Tree::new(&opts.clone().upstream("api")).unwrap();
+    let actions = tree.actions();
+    assert_eq!(actions[0].goals(), ["release"]);
+    assert_eq!(actions[0].unblocks(), 3);
+    match Tree::new(&opts.upstream("nope")) {
+        Err(e) => assert_eq!(e.code(), "ERR-003", "{}", e),
+        _ => panic!("ERR-944: a missing upstream todo is accepted"),
+    }
+}
+
+#[test]
+fn why() {
+    let opts = TreeOptions::new().input("examples/statuses.md");
+    let text = |name: &str| match why::why(&opts, name) {
+        Ok(why) => why.to_string(),
+        Err(e) => panic!("ERR-945: {}, {}", name, e),
+    };
+    assert_eq!(
+        text("move"),
+        "move is Pending, blocked by 2 todos\n\
+        - pack (InProgress, Ann): move -> pack\n\
+        - deposit (Waiting): move -> lease -> deposit\n"
+    );
+    assert_eq!(text("pack"), "pack is InProgress, nothing blocks it\n");
+    assert_eq!(
+        text("deposit"),
+        "deposit is Waiting, as its '- * ' line says\n"
+    );
+    let opts = TreeOptions::new()
+        .input("examples/dates.md")
+        .today(Date::new(2026, 10, 17).unwrap());
+    let why = why::why(&opts, "launch").unwrap();
+    let blockers: Vec<String> = why
+        .blockers()
+        .iter()
+        .map(|b| format!("{}:{}", b.todo().name(), b.chain().len()))
+        .collect();
+    assert_eq!(blockers, ["poster:2", "venue:2", "tickets:2"]);
+    match why::why(&opts, "nope") {
+        Err(e) => assert_eq!(e.code(), "ERR-003", "{}", e),
+        _ => panic!("ERR-946: a missing todo is explained"),
+    }
+    let opts = TreeOptions::new()
+        .input("examples/tags.md")
+        .exclude_tags(vec!["infra".to_string()]);
+    let blockers: Vec<String> = why::why(&opts, "login")
+        .unwrap()
+        .blockers()
+        .iter()
+        .map(|b| b.chain().join(" -> "))
+        .collect();
+    assert_eq!(blockers, ["login -> database"]);
+}
+
+#[test]
+fn theme() {
+    let theme = Theme::named_or_file("examples/theme.toml").unwrap();
+    let dark: Theme = "dark".parse().unwrap();
+    assert_eq!(theme.completed, dark.completed);
+    assert_eq!(theme.actionable, "#ff5f00".parse().unwrap());
+    assert_eq!(theme.owner, "245".parse().unwrap());
+    assert_eq!(theme.border, theme::Paint::default());
+    let opts = TreeOptions::new()
+        .input("examples/todotree.md")
+        .term_width(80)
+        .theme(theme.clone());
+    let text = Tree::new(&opts).unwrap().to_string();
+    assert!(
+        text.contains("\x1b[38;5;202m") && !text.contains("\x1b[38;2;"),
+        "ERR-961: 24-bit colors by default"
+    );
+    let paint = theme.actionable;
+    assert_eq!(
+        paint.apply("a", Format::Term, true),
+        "\x1b[38;2;255;95;0ma\x1b(B\x1b[m"
+    );
+    assert_eq!(
+        paint.apply("a", Format::Term, false),
+        "\x1b[38;5;202ma\x1b(B\x1b[m"
+    );
+    assert_eq!(
+        theme.cancelled.apply("a", Format::Html, true),
+        "<span style='color:gray; text-decoration:line-through'>a</span>"
+    );
+    match "#12345".parse::<theme::Color>() {
+        Err(e) => assert_eq!(e.code(), "ERR-043", "{}", e),
+        _ => panic!("ERR-932: color '#12345' is accepted"),
+    }
+    match Theme::named_or_file("nope") {
+        Err(e) => assert_eq!(e.code(), "ERR-042", "{}", e),
+        _ => panic!("ERR-933: theme 'nope' is accepted"),
+    }
+}
+
+#[test]
+fn config() {
+    let path = std::path::PathBuf::from("tests/config/.todotree.toml");
+    let config = Config::from_files(&[path]).unwrap();
+    assert!(config.flag("sort") && !config.flag("reverse"));
+    assert_eq!(config.string("depth"), Some("2".to_string()));
+    assert_eq!(config.theme(), Some(&"colorblind".parse().unwrap()));
+    let inputs = config.strings("input");
+    assert_eq!(inputs, ["tests/config/../../examples/teams.md"]);
+    let opts = TreeOptions::new()
+        .inputs(inputs)
+        .owners(config.strings("owner"));
+    let describe = |t: &Todo| t.name().to_string();
+    assert_eq!(shown(&opts, describe), "launch api schema");
+    let path = std::path::PathBuf::from("tests/config/bad.toml");
+    match Config::from_files(&[path]) {
+        Err(e) => assert_eq!(e.code(), "ERR-044", "{}", e),
+        _ => panic!("ERR-934: a flag set to a string is accepted"),
+    }
+}
+
+#[test]
+fn json_escape() {
+    assert_eq!(json_quote("a \"b\" \\c"), "\"a \\\"b\\\" \\\\c\"");
+    assert_eq!(json_quote("tab\tnl\n\u{1}"), "\"tab\\tnl\\n\\u0001\"");
+    assert_eq!(
+        json_array(&["x".to_string(), "y".to_string()]),
+        "[\"x\", \"y\"]"
+    );
+}
+
+#[test]
+fn json_input() {
+    for path in read_dir("examples/output").unwrap() {
+        let json = path.unwrap().path().display().to_string();
+        if !json.ends_with(".json") {
+            continue;
+        }
+        let result = Tree::new(
+            &TreeOptions::new()
+                .input(&json)
+                .term_width(80)
+                .auto_add(true)
+                .sort(true)
+                .today(Date::new(2026, 10, 17).unwrap()),
+        );
+        let tree = match result {
+            Ok(t) => t,
+            Err(e) => panic!("ERR-911: json: {}, {}", json, e),
+        };
+        let mut output = String::new();
+        match write!(output, "{}", tree) {
+            Ok(s) => s,
+            Err(e) => panic!("ERR-912: Failed to write '{}'", e),
+        }
+        let term = json.replace(".json", ".term");
+        let standard = match read_to_string(&term) {
+            Ok(s) => s,
+            Err(e) => panic!("ERR-913: json: {}, {}", json, e),
+        };
+        assert!(standard == output, "ERR-914: json: {}", json);
+        let md = json
+            .replace("examples/output/", "examples/")
+            .replace(".json", ".md");
+        let shown = |input: &str| {
+            let opts = TreeOptions::new()
+                .input(input)
+                .term_width(80)
+                .auto_add(true)
+                .sort(true)
+                .hide_comment(true)
+                .today(Date::new(2026, 10, 17).unwrap());
+            match Tree::new(&opts) {
+                Ok(tree) => tree.to_string(),
+                Err(e) => panic!("ERR-950: json: {}, {}", input, e),
+            }
+        };
+        assert!(shown(&json) == shown(&md), "ERR-951: json: {}", json);
+    }
+    let dir = temp_dir("json-input");
+    let json = dir.join("filtered.json").display().to_string();
+    let opts = TreeOptions::new()
+        .input("examples/todotree.md")
+        .format(Format::Json)
+        .hide_done(true)
+        .depth(-1)
+        .owners(vec!["Mom".to_string()]);
+    std::fs::write(&json, Tree::new(&opts).unwrap().to_string()).unwrap();
+    if let Err(e) = Tree::new(&TreeOptions::new().input(&json)) {
+        panic!("ERR-958: filtered json: {}", e);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edit_mark() {
+    let dir = temp_dir("edit-mark");
+    let md = dir.join("todotree.md").display().to_string();
+    std::fs::copy("examples/todotree.md", &md).unwrap();
+    let opts = TreeOptions::new().input(&md);
+    match edit::mark(&opts, "movie", edit::Mark::Done, false) {
+        Err(e) => assert_eq!(e.code(), "ERR-004", "{}", e),
+        _ => panic!("ERR-915: movie is done before its dependencies"),
+    }
+    assert!(edit::mark(&opts, "dinner", edit::Mark::Done, false).unwrap());
+    assert!(!edit::mark(&opts, "mower", edit::Mark::Toggle, false).unwrap());
+    let expected = read_to_string("examples/todotree.md")
+        .unwrap()
+        .replace("# dinner\n", "# ~~dinner~~\n")
+        .replace("# ~~mower~~\n", "# mower\n");
+    assert!(read_to_string(&md).unwrap() == expected, "ERR-916: {}", md);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn command_names() {
+    let dir = temp_dir("command-names");
+    let md = dir.join("commands.md").display().to_string();
+    std::fs::write(&md, "# check\n- : run\n\n# run\n").unwrap();
+    let opts = TreeOptions::new().input(&md);
+    assert!(edit::is_todo(&opts, "check"), "ERR-952: check is a todo");
+    assert!(edit::is_todo(&opts, "run"), "ERR-953: run is a todo");
+    assert!(!edit::is_todo(&opts, "fmt"), "ERR-954: fmt is not a todo");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edit_filtered() {
+    let dir = temp_dir("edit-filtered");
+    let md = dir.join("tags.md").display().to_string();
+    std::fs::copy("examples/tags.md", &md).unwrap();
+    let opts = TreeOptions::new()
+        .input(&md)
+        .tags(vec!["backend".to_string()]);
+    match edit::add(&opts, "database", &[], &[], &[]) {
+        Err(e) => assert_eq!(e.code(), "ERR-014", "{}", e),
+        _ => panic!("ERR-947: a filtered out todo is added twice"),
+    }
+    assert!(edit::mark(&opts, "database", edit::Mark::Done, false).unwrap());
+    let expected = read_to_string("examples/tags.md")
+        .unwrap()
+        .replace("# database\n", "# ~~database~~\n");
+    assert!(read_to_string(&md).unwrap() == expected, "ERR-948: {}", md);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edit_add_link() {
+    let dir = temp_dir("edit-add-link");
+    let md = dir.join("todotree.md").display().to_string();
+    std::fs::copy("examples/todotree.md", &md).unwrap();
+    let opts = TreeOptions::new().input(&md);
+    let deps = vec!["dinner".to_string()];
+    let comments = vec!["buy online".to_string()];
+    let owners = vec!["Mom".to_string(), "Dad".to_string()];
+    edit::add(&opts, "tickets", &owners, &deps, &comments).unwrap();
+    let json = TreeOptions::new().input("examples/output/todotree.json");
+    for (result, code) in [
+        (edit::add(&json, "tickets", &[], &[], &[]), "ERR-026"),
+        (
+            edit::add(
+                &opts,
+                "cake",
+                &["Ann".into(), "Bob, Ann".into()],
+                &[],
+                &[],
+            ),
+            "ERR-008",
+        ),
+        (edit::add(&opts, "tickets", &[], &[], &[]), "ERR-014"),
+        (edit::add(&opts, "a b", &[], &[], &[]), "ERR-001"),
+        (edit::link(&opts, "dinner", "movie"), "ERR-002"),
+        (edit::link(&opts, "garden", "lawn"), "ERR-004"),
+        (edit::unlink(&opts, "dinner", "lawn"), "ERR-031"),
+    ] {
+        match result {
+            Err(e) => assert_eq!(e.code(), code, "{}", e),
+            _ => panic!("ERR-917: {} is not raised", code),
+        }
+    }
+    edit::link(&opts, "movie", "tickets").unwrap();
+    edit::unlink(&opts, "movie", "garden").unwrap();
+    let expected = read_to_string("examples/todotree.md")
+        .unwrap()
+        .replace("- : garden dinner lawn\n", "- : dinner lawn tickets\n")
+        + "\n# tickets\n- @ Mom\n- @ Dad\n- : dinner\n- % buy online\n";
+    assert!(read_to_string(&md).unwrap() == expected, "ERR-918: {}", md);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edit_unlink() {
+    let dir = temp_dir("edit-unlink");
+    let a = dir.join("a.md").display().to_string();
+    let b = dir.join("b.md").display().to_string();
+    std::fs::write(&a, "# p\n  - :  x   x@b\n- :  y\n# x\n# y\n").unwrap();
+    std::fs::write(&b, "# x\n").unwrap();
+    let opts = TreeOptions::new().input(&a).input(&b);
+    edit::unlink(&opts, "p", "x").unwrap();
+    assert_eq!(
+        read_to_string(&a).unwrap(),
+        "# p\n  - : x@b\n- :  y\n# x\n# y\n",
+        "ERR-949: {}",
+        a
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edit_rename() {
+    let dir = temp_dir("edit-rename");
+    let a = dir.join("a.md").display().to_string();
+    let b = dir.join("b.md").display().to_string();
+    std::fs::copy("examples/todotree.md", &a).unwrap();
+    std::fs::write(&b, "# party\n- : movie@a ~cake\n").unwrap();
+    let opts = TreeOptions::new().input(&a).input(&b).auto_add(true);
+    for (new, code) in [("dinner", "ERR-014"), ("x@y", "ERR-017")] {
+        match edit::rename(&opts, "movie", new) {
+            Err(e) => assert_eq!(e.code(), code, "{}", e),
+            _ => panic!("ERR-919: {} is not raised", code),
+        }
+    }
+    edit::rename(&opts, "movie", "film").unwrap();
+    edit::rename(&opts, "mower", "mower2").unwrap();
+    let expected = read_to_string("examples/todotree.md")
+        .unwrap()
+        .replace("# movie\n", "# film\n")
+        .replace("- : mower\n", "- : mower2\n")
+        .replace("# ~~mower~~\n", "# ~~mower2~~\n");
+    assert!(read_to_string(&a).unwrap() == expected, "ERR-920: {}", a);
+    let expected = "# party\n- : film@a ~cake\n";
+    assert!(read_to_string(&b).unwrap() == expected, "ERR-921: {}", b);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edit_escaped() {
+    let dir = temp_dir("edit-escaped");
+    let md = dir.join("escaped.md").display().to_string();
+    std::fs::write(&md, "# a\\_b\n- : c\\_d\n\n# c\\_d\n").unwrap();
+    let opts = TreeOptions::new().input(&md);
+    assert!(edit::mark(&opts, "c_d", edit::Mark::Done, false).unwrap());
+    let expected = "# a\\_b\n- : c\\_d\n\n# ~~c\\_d~~\n";
+    assert_eq!(read_to_string(&md).unwrap(), expected, "ERR-955: {}", md);
+    assert_eq!(edit::format_md(expected), expected, "ERR-956: fmt");
+    edit::rename(&opts, "c_d", "e").unwrap();
+    let expected = "# a\\_b\n- : e\n\n# ~~e~~\n";
+    assert_eq!(read_to_string(&md).unwrap(), expected, "ERR-957: {}", md);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fmt() {
+    let input = read_to_string("tests/fmt/in.md").unwrap();
+    let standard = read_to_string("tests/fmt/out.md").unwrap();
+    assert!(edit::format_md(&input) == standard, "ERR-922: fmt");
+    assert!(edit::format_md(&standard) == standard, "ERR-923: fmt");
+}
+
+#[test]
+fn run_recipes() {
+    let dir = temp_dir("run-recipes");
+    let md = dir.join("recipes.md").display().to_string();
+    std::fs::copy("examples/recipes.md", &md).unwrap();
+    let opts = TreeOptions::new().input(&md);
+    let target = vec!["release".to_string()];
+    let steps = run::plan(&opts, &target).unwrap();
+    let names: Vec<&str> = steps.iter().map(|s| s.name()).collect();
+    assert_eq!(names, ["build", "test", "release"], "ERR-935");
+    let mut out = Vec::new();
+    run::run(&opts, &steps, 2, &mut out).unwrap();
+    assert!(dir.join("dist/app").is_file(), "ERR-936: {:?}", dir);
+    let expected = read_to_string("examples/recipes.md")
+        .unwrap()
+        .replace("# release\n", "# ~~release~~\n")
+        .replace("# build\n", "# ~~build~~\n")
+        .replace("# test\n", "# ~~test~~\n");
+    assert!(read_to_string(&md).unwrap() == expected, "ERR-937: {}", md);
+    assert!(run::plan(&opts, &target).unwrap().is_empty(), "ERR-938");
+    std::fs::write(&md, "# a\n- : b c\n- $ true\n# b\n- $ false\n# c\n")
+        .unwrap();
+    for (target, code) in [("a", "ERR-045"), ("b", "ERR-046")] {
+        let result = run::plan(&opts, &[target.to_string()])
+            .and_then(|steps| run::run(&opts, &steps, 1, &mut Vec::new()));
+        match result {
+            Err(e) => assert_eq!(e.code(), code, "{}", e),
+            _ => panic!("ERR-939: {} is not raised", code),
+        }
+    }
+    assert!(read_to_string(&md).unwrap().contains("# b\n"), "ERR-940");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,381 @@
+use super::{Format, Status, TodoError};
+use std::fs::read_to_string;
+use std::str::FromStr;
+use toml::{Table, Value};
+
+/// Names of the 16 basic colors, with the CSS color used for each one, which
+/// is readable on a white page
+static BASIC: [(&str, &str); 16] = [
+    ("black", "black"),
+    ("red", "red"),
+    ("green", "green"),
+    ("yellow", "orange"),
+    ("blue", "blue"),
+    ("magenta", "magenta"),
+    ("cyan", "darkcyan"),
+    ("white", "lightgray"),
+    ("gray", "gray"),
+    ("bright-red", "tomato"),
+    ("bright-green", "lime"),
+    ("bright-yellow", "gold"),
+    ("bright-blue", "dodgerblue"),
+    ("bright-magenta", "violet"),
+    ("bright-cyan", "cyan"),
+    ("bright-white", "white"),
+];
+
+/// Intensities of the 6x6x6 color cube of 256-color terminals
+static CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Names of the built-in themes
+static THEMES: [&str; 4] = ["default", "light", "dark", "colorblind"];
+
+/// A color, written as a basic color name, a 256-color index or #RRGGBB
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Color {
+    /// one of the 16 basic colors
+    Basic(u8),
+    /// 0 to 255, as in 256-color terminals
+    Fixed(u8),
+    /// 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Returns the SGR parameters, as 256 colors if not truecolor.
+    fn sgr(&self, truecolor: bool) -> String {
+        match *self {
+            Color::Basic(n) if n < 8 => (30 + n).to_string(),
+            Color::Basic(n) => (82 + n).to_string(),
+            Color::Fixed(n) => format!("38;5;{}", n),
+            Color::Rgb(r, g, b) if truecolor => {
+                format!("38;2;{};{};{}", r, g, b)
+            }
+            Color::Rgb(r, g, b) => {
+                let level = |v: u8| match v {
+                    0..48 => 0,
+                    48..115 => 1,
+                    _ => (v - 35) / 40,
+                };
+                format!("38;5;{}", 16 + 36 * level(r) + 6 * level(g) + level(b))
+            }
+        }
+    }
+
+    /// Returns the color for HTML, Graphviz and Mermaid.
+    pub(crate) fn css(&self) -> String {
+        let (r, g, b) = match *self {
+            Color::Basic(n) | Color::Fixed(n) if n < 16 => {
+                return BASIC[n as usize].1.to_string();
+            }
+            Color::Fixed(n) if n >= 232 => {
+                let v = 8 + 10 * (n - 232);
+                (v, v, v)
+            }
+            Color::Fixed(n) => {
+                let n = (n - 16) as usize;
+                (CUBE[n / 36], CUBE[n / 6 % 6], CUBE[n % 6])
+            }
+            Color::Basic(_) => unreachable!(),
+            Color::Rgb(r, g, b) => (r, g, b),
+        };
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl FromStr for Color {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || TodoError::BadColor {
+            text: s.to_string(),
+        };
+        if let Some(idx) = BASIC.iter().position(|(name, _)| *name == s) {
+            return Ok(Color::Basic(idx as u8));
+        }
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(bad());
+            }
+            let num = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            return Ok(Color::Rgb(
+                num(0).unwrap(),
+                num(2).unwrap(),
+                num(4).unwrap(),
+            ));
+        }
+        s.parse().map(Color::Fixed).map_err(|_| bad())
+    }
+}
+
+/// How a part of the output is highlighted, written as a color, 'strike'
+/// and 'bold', 'none' for none of them
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct Paint {
+    pub(crate) color: Option<Color>,
+    /// strikethrough
+    pub(crate) strike: bool,
+    pub(crate) bold: bool,
+}
+
+impl Paint {
+    const fn color(color: Color) -> Self {
+        Paint {
+            color: Some(color),
+            strike: false,
+            bold: false,
+        }
+    }
+
+    const fn struck(color: Color) -> Self {
+        Paint {
+            color: Some(color),
+            strike: true,
+            bold: false,
+        }
+    }
+
+    const fn bold() -> Self {
+        Paint {
+            color: None,
+            strike: false,
+            bold: true,
+        }
+    }
+
+    /// Returns this highlight added to another one, its color if any
+    /// replacing the other one.
+    pub(crate) fn over(&self, base: &Paint) -> Paint {
+        Paint {
+            color: self.color.or(base.color),
+            strike: self.strike || base.strike,
+            bold: self.bold || base.bold,
+        }
+    }
+
+    /// Returns text highlighted for a format, or as is if there is nothing
+    /// to highlight.
+    pub(crate) fn apply(
+        &self,
+        text: &str,
+        format: Format,
+        truecolor: bool,
+    ) -> String {
+        if *self == Paint::default() || text.is_empty() {
+            return text.to_string();
+        }
+        match format {
+            Format::Term => {
+                let mut sgr = vec![];
+                if self.bold {
+                    sgr.push("1".to_string());
+                }
+                if self.strike {
+                    sgr.push("9".to_string());
+                }
+                sgr.extend(self.color.map(|c| c.sgr(truecolor)));
+                format!("\x1b[{}m{}\x1b(B\x1b[m", sgr.join(";"), text)
+            }
+            Format::Html => {
+                let mut css = vec![];
+                css.extend(self.color.map(|c| format!("color:{}", c.css())));
+                if self.bold {
+                    css.push("font-weight:bold".to_string());
+                }
+                if self.strike {
+                    css.push("text-decoration:line-through".to_string());
+                }
+                format!("<span style='{}'>{}</span>", css.join("; "), text)
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+impl FromStr for Paint {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut paint = Paint::default();
+        for word in s.split_whitespace() {
+            match word {
+                "none" => {}
+                "strike" => paint.strike = true,
+                "bold" => paint.bold = true,
+                _ => paint.color = Some(word.parse()?),
+            }
+        }
+        Ok(paint)
+    }
+}
+
+/// Colors of the statuses and of the table parts
+#[derive(PartialEq, Clone, Debug)]
+pub struct Theme {
+    pub(crate) completed: Paint,
+    pub(crate) pending: Paint,
+    pub(crate) actionable: Paint,
+    pub(crate) in_progress: Paint,
+    pub(crate) blocked: Paint,
+    pub(crate) cancelled: Paint,
+    pub(crate) waiting: Paint,
+    /// the name of an overdue todo, instead of its status color
+    pub(crate) overdue: Paint,
+    /// the PRIO cell of a P1 todo
+    pub(crate) priority: Paint,
+    /// the names on a critical path, over their status color
+    pub(crate) critical: Paint,
+    pub(crate) owner: Paint,
+    pub(crate) comment: Paint,
+    /// the box-drawing characters
+    pub(crate) border: Paint,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            completed: Paint::color(Color::Basic(4)),
+            pending: Paint::default(),
+            actionable: Paint::color(Color::Basic(1)),
+            in_progress: Paint::color(Color::Basic(2)),
+            blocked: Paint::color(Color::Basic(3)),
+            cancelled: Paint::struck(Color::Basic(8)),
+            waiting: Paint::color(Color::Basic(6)),
+            overdue: Paint::color(Color::Basic(5)),
+            priority: Paint::default(),
+            critical: Paint::bold(),
+            owner: Paint::default(),
+            comment: Paint::default(),
+            border: Paint::default(),
+        }
+    }
+}
+
+impl Theme {
+    /// Returns the highlight of a status.
+    pub(crate) fn status(&self, status: Status) -> &Paint {
+        match status {
+            Status::Completed => &self.completed,
+            Status::Pending => &self.pending,
+            Status::Actionable => &self.actionable,
+            Status::InProgress => &self.in_progress,
+            Status::Blocked => &self.blocked,
+            Status::Cancelled => &self.cancelled,
+            Status::Waiting => &self.waiting,
+        }
+    }
+
+    /// Returns a built-in theme, or the theme of a TOML file.
+    pub fn named_or_file(value: &str) -> Result<Self, TodoError> {
+        if THEMES.contains(&value) || !value.ends_with(".toml") {
+            return value.parse();
+        }
+        let text = read_to_string(value)?;
+        let table: Table =
+            text.parse().map_err(|e: toml::de::Error| TodoError::Toml {
+                file: value.to_string(),
+                reason: e.message().to_string(),
+            })?;
+        Theme::from_table(&table, value)
+    }
+
+    /// Creates a theme from a TOML table, such as
+    ///
+    /// ```toml
+    /// base = "dark"
+    /// actionable = "#ff5f00"
+    /// owner = "245"
+    /// cancelled = "gray strike"
+    /// ```
+    ///
+    /// The keys missing in the table are taken from the base theme, or from
+    /// the default one.
+    pub(crate) fn from_table(
+        table: &Table,
+        file: &str,
+    ) -> Result<Self, TodoError> {
+        let invalid = |reason: String| TodoError::Toml {
+            file: file.to_string(),
+            reason,
+        };
+        let mut theme = match table.get("base") {
+            Some(Value::String(base)) => base.parse()?,
+            Some(_) => return Err(invalid("'base' is not a string".into())),
+            None => Theme::default(),
+        };
+        for (key, value) in table {
+            let paint = match (key.as_str(), value) {
+                ("base", _) => continue,
+                ("completed", _) => &mut theme.completed,
+                ("pending", _) => &mut theme.pending,
+                ("actionable", _) => &mut theme.actionable,
+                ("in-progress", _) => &mut theme.in_progress,
+                ("blocked", _) => &mut theme.blocked,
+                ("cancelled", _) => &mut theme.cancelled,
+                ("waiting", _) => &mut theme.waiting,
+                ("overdue", _) => &mut theme.overdue,
+                ("priority", _) => &mut theme.priority,
+                ("critical", _) => &mut theme.critical,
+                ("owner", _) => &mut theme.owner,
+                ("comment", _) => &mut theme.comment,
+                ("border", _) => &mut theme.border,
+                _ => return Err(invalid(format!("unknown key '{}'", key))),
+            };
+            *paint = match value {
+                Value::String(s) => s.parse()?,
+                Value::Integer(n) => n.to_string().parse()?,
+                _ => return Err(invalid(format!("'{}' is not a color", key))),
+            };
+        }
+        Ok(theme)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = TodoError;
+
+    /// Returns a built-in theme.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fixed = |n| Paint::color(Color::Fixed(n));
+        let rgb = |r, g, b| Paint::color(Color::Rgb(r, g, b));
+        match s {
+            "default" => Ok(Theme::default()),
+            "light" => Ok(Theme {
+                priority: Paint::color(Color::Basic(1)),
+                owner: fixed(25),
+                comment: fixed(242),
+                border: fixed(248),
+                ..Theme::default()
+            }),
+            "dark" => Ok(Theme {
+                completed: fixed(75),
+                pending: Paint::default(),
+                actionable: fixed(203),
+                in_progress: fixed(114),
+                blocked: fixed(221),
+                cancelled: Paint::struck(Color::Fixed(244)),
+                waiting: fixed(80),
+                overdue: fixed(213),
+                priority: fixed(209),
+                critical: Paint::bold(),
+                owner: fixed(180),
+                comment: fixed(250),
+                border: fixed(240),
+            }),
+            // the Okabe-Ito palette
+            "colorblind" => Ok(Theme {
+                completed: rgb(0, 114, 178),
+                actionable: rgb(213, 94, 0),
+                in_progress: rgb(0, 158, 115),
+                blocked: rgb(230, 159, 0),
+                waiting: rgb(86, 180, 233),
+                overdue: rgb(204, 121, 167),
+                priority: rgb(213, 94, 0),
+                ..Theme::default()
+            }),
+            _ => Err(TodoError::Theme {
+                value: s.to_string(),
+            }),
+        }
+    }
+}
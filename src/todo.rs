@@ -0,0 +1,1011 @@
+use super::{
+    Date, Estimate, Format, HTMLP, Position, Priority, ROOT, Status, Theme,
+    TodoError, json_array, json_quote, theme::Paint, tree::TreeOptions,
+};
+use std::cell::RefCell;
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::rc::Rc;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Number of table columns: name, owner, priority, estimate, tags, date and
+/// comment
+pub(crate) const COLUMNS: usize = 7;
+/// Index of the comment column
+const COMMENT: usize = COLUMNS - 1;
+/// Headers of the columns between name and comment
+static HEADERS: [&str; COMMENT - 1] = ["OWNER", "PRIO", "EST", "TAGS", "DATE"];
+
+#[derive(PartialEq)]
+enum Location {
+    Top,
+    Mid,
+    Bottom,
+}
+
+/// Rendering settings shared by all rows of a tree
+pub(crate) struct Style<'a> {
+    /// maximum length of each column
+    maxwidth: &'a [usize; COLUMNS],
+    format: Format,
+    /// a space character
+    space: &'static str,
+    /// beginning of a line
+    bol: &'static str,
+    /// end of a line
+    eol: &'static str,
+    /// None for no color
+    theme: Option<&'a Theme>,
+    truecolor: bool,
+    reverse: bool,
+}
+
+impl<'a> Style<'a> {
+    pub(crate) fn new(
+        maxwidth: &'a [usize; COLUMNS],
+        opts: &'a TreeOptions,
+    ) -> Self {
+        let format = opts.format;
+        let space = match format {
+            Format::Html => "&nbsp;",
+            _ => " ",
+        };
+        let (bol, eol) = match format {
+            Format::Term => ("", "\n"),
+            Format::Html => (HTMLP, "</p>\n"),
+            _ => ("", ""),
+        };
+        Style {
+            maxwidth,
+            format,
+            space,
+            bol,
+            eol,
+            theme: (!opts.no_color).then_some(&opts.theme),
+            truecolor: opts.truecolor,
+            reverse: opts.reverse,
+        }
+    }
+
+    /// Returns text highlighted by a part of the theme.
+    fn paint(
+        &self,
+        part: impl FnOnce(&'a Theme) -> &'a Paint,
+        text: &str,
+    ) -> String {
+        match self.theme {
+            Some(theme) => part(theme).apply(text, self.format, self.truecolor),
+            None => text.to_string(),
+        }
+    }
+
+    /// Returns the name of a todo highlighted by its status, or as overdue,
+    /// and as critical if it is on a critical path.
+    fn name(&self, todo: &Todo) -> String {
+        let Some(theme) = self.theme else {
+            return todo.name.clone();
+        };
+        let mut paint = match todo.overdue {
+            true => theme.overdue,
+            false => *theme.status(todo.status),
+        };
+        if todo.critical {
+            paint = theme.critical.over(&paint);
+        }
+        paint.apply(&todo.name, self.format, self.truecolor)
+    }
+
+    /// Returns box-drawing characters highlighted as borders.
+    fn border(&self, text: &str) -> String {
+        self.paint(|t| &t.border, text)
+    }
+}
+
+/// A todo and the todos it depends on
+pub struct Todo {
+    pub(crate) name: String,
+    /// the '- @ ' lines, people or teams
+    pub(crate) owners: Vec<String>,
+    pub(crate) comment: Vec<String>,
+    pub(crate) dependencies: Vec<String>,
+    /// the todos depending on it, its children in an upstream tree
+    pub(crate) dependents: Vec<String>,
+    /// the '- ! ' line
+    pub(crate) due: Option<Date>,
+    /// the '- > ' line
+    pub(crate) start: Option<Date>,
+    /// the '- ^ ' line
+    pub(crate) priority: Option<Priority>,
+    /// the highest priority of itself and the todos depending on it
+    pub(crate) effective: Option<Priority>,
+    /// the '- = ' line
+    pub(crate) estimate: Option<Estimate>,
+    /// the '- # ' lines
+    pub(crate) tags: Vec<String>,
+    /// the '- $ ' lines, each a shell command
+    pub(crate) recipe: Vec<String>,
+    /// the markdown file lines following each todo
+    pub(crate) auxilaries: Vec<String>,
+    /// the markdown file defining it, empty if auto-added
+    pub(crate) file: String,
+    /// the '# ' line
+    pub(crate) position: Position,
+    /// the '- : ' line of each dependency, by name without '~'
+    pub(crate) dep_positions: BTreeMap<String, Position>,
+    pub(crate) children: Vec<Rc<RefCell<Todo>>>,
+    /// the depth based on its deepest child
+    depth: i32,
+    pub(crate) status: Status,
+    /// actionable with a due date before today
+    overdue: bool,
+    /// on the longest chain of uncompleted todos from a target
+    pub(crate) critical: bool,
+    /// shown for itself by the owner, tag and priority filters, rather than
+    /// for a todo it depends on
+    pub(crate) matched: bool,
+}
+
+impl Todo {
+    pub fn new(
+        name: String,
+        status: Status,
+        owners: Vec<String>,
+        comment: Vec<String>,
+        dependencies: Vec<String>,
+        auxilaries: Vec<String>,
+    ) -> Result<Self, TodoError> {
+        static SPACES: [char; 4] = [' ', '\u{00A0}', '\u{2009}', '\u{3000}'];
+        if name != ROOT {
+            if name.ends_with(ROOT) {
+                return Err(TodoError::SlashInName {
+                    name: name.to_string(),
+                });
+            }
+            for c in name.chars() {
+                if SPACES.contains(&c) {
+                    return Err(TodoError::SpaceInName {
+                        name: name.to_string(),
+                        space: c,
+                    });
+                }
+            }
+        }
+        Ok(Todo {
+            name,
+            owners,
+            comment,
+            status,
+            dependencies,
+            dependents: Vec::new(),
+            due: None,
+            start: None,
+            priority: None,
+            effective: None,
+            estimate: None,
+            tags: Vec::new(),
+            recipe: Vec::new(),
+            auxilaries,
+            file: String::new(),
+            position: Position::default(),
+            dep_positions: BTreeMap::new(),
+            children: Vec::new(),
+            depth: 0,
+            overdue: false,
+            critical: false,
+            matched: false,
+        })
+    }
+
+    /// Sets where it is defined in a markdown file.
+    pub(crate) fn located(
+        mut self,
+        position: Position,
+        dep_positions: BTreeMap<String, Position>,
+    ) -> Self {
+        self.position = position;
+        self.dep_positions = dep_positions;
+        self
+    }
+
+    /// Sets the due and start dates.
+    pub(crate) fn dated(
+        mut self,
+        due: Option<Date>,
+        start: Option<Date>,
+    ) -> Self {
+        self.due = due;
+        self.start = start;
+        self
+    }
+
+    /// Sets the priority.
+    pub(crate) fn prioritized(mut self, priority: Option<Priority>) -> Self {
+        self.priority = priority;
+        self.effective = priority;
+        self
+    }
+
+    /// Sets the estimate.
+    pub(crate) fn estimated(mut self, estimate: Option<Estimate>) -> Self {
+        self.estimate = estimate;
+        self
+    }
+
+    /// Sets the tags.
+    pub(crate) fn tagged(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Sets the recipe.
+    pub(crate) fn scripted(mut self, recipe: Vec<String>) -> Self {
+        self.recipe = recipe;
+        self
+    }
+
+    /// Returns a copy for an upstream tree, with the todos depending on it as
+    /// children, and the status it has in the dependency tree.
+    pub(crate) fn upstream(&self, dependents: Vec<String>) -> Self {
+        Todo {
+            name: self.name.clone(),
+            owners: self.owners.clone(),
+            comment: self.comment.clone(),
+            dependencies: self.dependencies.clone(),
+            dependents,
+            due: self.due,
+            start: self.start,
+            priority: self.priority,
+            effective: self.effective,
+            estimate: self.estimate,
+            tags: self.tags.clone(),
+            recipe: self.recipe.clone(),
+            auxilaries: self.auxilaries.clone(),
+            file: self.file.clone(),
+            position: self.position.clone(),
+            dep_positions: self.dep_positions.clone(),
+            children: Vec::new(),
+            depth: 0,
+            status: self.status,
+            overdue: self.overdue,
+            critical: false,
+            matched: false,
+        }
+    }
+
+    /// Returns the name, with a trailing '/' if its children are cut by depth.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the owners, people or teams.
+    pub fn owners(&self) -> &[String] {
+        &self.owners
+    }
+
+    /// Returns the status computed from the dependencies.
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Returns the comment lines.
+    pub fn comments(&self) -> &[String] {
+        &self.comment
+    }
+
+    /// Returns the dependency names, '~' prefixed if completed.
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    /// Returns the due date, if any.
+    pub fn due(&self) -> Option<Date> {
+        self.due
+    }
+
+    /// Returns the start date, if any.
+    pub fn start(&self) -> Option<Date> {
+        self.start
+    }
+
+    /// Returns true if it is actionable and its due date has passed.
+    pub fn is_overdue(&self) -> bool {
+        self.overdue
+    }
+
+    /// Returns the priority, if any.
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    /// Returns the highest priority of itself and the todos depending on it.
+    pub fn effective_priority(&self) -> Option<Priority> {
+        self.effective
+    }
+
+    /// Returns the estimate, if any.
+    pub fn estimate(&self) -> Option<Estimate> {
+        self.estimate
+    }
+
+    /// Returns true if it is on a critical path, see '--critical-path'.
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Returns the tags.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns the shell commands of the recipe.
+    pub fn recipe(&self) -> &[String] {
+        &self.recipe
+    }
+
+    /// Returns the markdown file defining it, empty if auto-added.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the '# ' line, with line 0 if auto-added.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Returns the dependencies shown in the tree.
+    pub fn children(&self) -> &[Rc<RefCell<Todo>>] {
+        &self.children
+    }
+
+    pub(crate) fn build_tree(
+        &mut self,
+        visited: &mut BTreeSet<String>,
+        map: &BTreeMap<String, Rc<RefCell<Todo>>>,
+        path: &mut Vec<String>,
+        depth: usize,
+        opts: &TreeOptions,
+        owners: &mut BTreeMap<String, (BTreeSet<String>, bool)>,
+    ) -> Result<bool, TodoError> {
+        let dpth_limit = opts.depth;
+        let mut own_me = owners.is_empty();
+        for (members, used) in owners.values_mut() {
+            if self.owners.iter().any(|o| members.contains(o)) {
+                *used = true;
+                own_me = true;
+            }
+        }
+        if let Some(priority) = opts.priority {
+            own_me = own_me && self.effective.is_some_and(|p| p <= priority);
+        }
+        if !opts.tags.is_empty() {
+            own_me = own_me && self.tags.iter().any(|t| opts.tags.contains(t));
+        }
+        if self.tags.iter().any(|t| opts.exclude_tags.contains(t)) {
+            own_me = false;
+        }
+        self.matched = own_me;
+        if opts.hide_owner {
+            self.owners.clear();
+        }
+        let mut dependencies = match opts.upstream {
+            Some(_) if self.name != ROOT => self.dependents.clone(),
+            _ => self.dependencies.clone(),
+        };
+        if opts.by_priority {
+            dependencies.sort_by_key(|dep| {
+                let effective = map
+                    .get(&dep.replace("~", ""))
+                    .and_then(|child| child.borrow().effective);
+                (effective.is_none(), effective)
+            });
+        }
+        let mut notdonedeps: Vec<String> = vec![];
+        for dep in &dependencies {
+            let dep = dep.replace("~", "");
+            let position = self.dep_position(&dep);
+            if let Some(idx) = path.iter().position(|p| *p == dep) {
+                return Err(TodoError::Loop {
+                    cycle: path[idx..].to_vec(),
+                }
+                .at(position));
+            }
+            path.push(dep.clone());
+            let child = match map.get(&dep) {
+                Some(m) => m,
+                None => {
+                    return Err(TodoError::Missing {
+                        name: dep.to_string(),
+                    }
+                    .at(position));
+                }
+            };
+            let dep_notdone = !child.borrow().status.is_done();
+            if dep_notdone {
+                notdonedeps.push(dep.clone());
+            }
+            if (dpth_limit <= 0 || dpth_limit > depth as i32)
+                && visited.insert(child.borrow().name.clone())
+            {
+                let own_child = child.borrow_mut().build_tree(
+                    visited,
+                    map,
+                    path,
+                    depth + 1,
+                    opts,
+                    owners,
+                )?;
+                if own_child {
+                    own_me = true;
+                    let child_depth = child.borrow().depth;
+                    self.depth = max(child_depth + 1, self.depth);
+                    if (dpth_limit >= 0 || child_depth + dpth_limit >= 0)
+                        && (dep_notdone || !opts.hide_done)
+                    {
+                        self.children.push(Rc::clone(child));
+                    }
+                }
+            }
+            path.pop();
+        }
+        if opts.upstream.is_some() {
+            // the status comes from the dependency tree
+        } else if notdonedeps.is_empty() {
+            let started = match (self.start, opts.today) {
+                (Some(start), Some(today)) => start <= today,
+                _ => true,
+            };
+            if self.status == Status::Pending && started {
+                self.status = Status::Actionable;
+            }
+            if matches!(self.status, Status::Actionable | Status::InProgress) {
+                self.overdue = match (self.due, opts.today) {
+                    (Some(due), Some(today)) => due < today,
+                    _ => false,
+                };
+            }
+        } else if self.status == Status::Completed {
+            return Err(TodoError::Incomplete {
+                name: self.name.clone(),
+                dependencies: notdonedeps,
+            }
+            .at(&self.position));
+        }
+        if self.name != ROOT
+            && !dependencies.is_empty()
+            && !self.name.ends_with(ROOT)
+            && ((dpth_limit > 0 && dpth_limit == depth as i32)
+                || (dpth_limit < 0 && self.depth + dpth_limit == 0))
+        {
+            self.name.push_str(ROOT)
+        }
+        Ok(own_me)
+    }
+
+    /// Returns the '- : ' line of a dependency, or the '# ' line.
+    pub(crate) fn dep_position(&self, dep: &str) -> &Position {
+        self.dep_positions.get(dep).unwrap_or(&self.position)
+    }
+
+    /// Collects the todos below this one in tree order, each once.
+    pub(crate) fn collect(
+        &self,
+        todos: &mut Vec<Rc<RefCell<Todo>>>,
+        visited: &mut BTreeSet<String>,
+    ) {
+        for child in &self.children {
+            if visited.insert(child.borrow().name.clone()) {
+                todos.push(Rc::clone(child));
+                child.borrow().collect(todos, visited);
+            }
+        }
+    }
+
+    pub(crate) fn get_maxwidth(
+        &mut self,
+        maxwidth: &mut [usize; COLUMNS],
+        depth: usize,
+        screen_width: usize,
+    ) -> Result<(), TodoError> {
+        for child in &self.children {
+            child.borrow_mut().get_maxwidth(
+                maxwidth,
+                depth + 1,
+                screen_width,
+            )?;
+        }
+        if self.name == ROOT && maxwidth[COMMENT] > 0 {
+            self.comment = vec!["COMMENT".to_string(); 1];
+        }
+        maxwidth[0] = max(maxwidth[0], depth * 4 + self.name.width());
+        for (idx, cell) in self.cells(maxwidth).iter().enumerate() {
+            maxwidth[idx + 1] = max(maxwidth[idx + 1], cell.width());
+        }
+        for line in &self.comment {
+            maxwidth[COMMENT] = max(maxwidth[COMMENT], line.width());
+        }
+        if self.comment.len() > 1 {
+            maxwidth[COMMENT] += self.comment.len().to_string().len() + 2;
+        }
+        if self.name == ROOT {
+            let cells: Vec<usize> = maxwidth[1..COMMENT]
+                .iter()
+                .filter(|w| **w > 0)
+                .map(|w| w + 3)
+                .collect();
+            // the first cell shares its separator with the name
+            let used = maxwidth[0] + 8 + cells.iter().sum::<usize>()
+                - min(cells.len(), 1) * 3;
+            if screen_width <= used {
+                return Err(TodoError::ScreenWidth {
+                    width: screen_width,
+                    needed: used + 1,
+                });
+            }
+            maxwidth[COMMENT] = min(maxwidth[COMMENT], screen_width - used);
+        }
+        Ok(())
+    }
+
+    /// Returns the cells between name and comment, headers for the root.
+    fn cells(&self, maxwidth: &[usize; COLUMNS]) -> [String; COMMENT - 1] {
+        if self.name == ROOT {
+            let mut headers = HEADERS.map(String::from);
+            for (idx, header) in headers.iter_mut().enumerate() {
+                if maxwidth[idx + 1] == 0 {
+                    header.clear();
+                }
+            }
+            return headers;
+        }
+        let mut dates = vec![];
+        if let Some(start) = self.start {
+            dates.push(format!(">{}", start));
+        }
+        if let Some(due) = self.due {
+            dates.push(format!("!{}", due));
+        }
+        let priority = self.effective.map(|p| p.to_string());
+        let estimate = self.estimate.map(|e| e.to_string());
+        [
+            self.owners.join(", "),
+            priority.unwrap_or_default(),
+            estimate.unwrap_or_default(),
+            self.tags.join(" "),
+            dates.join(" "),
+        ]
+    }
+
+    pub(crate) fn fmt_tree(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &mut Vec<bool>,
+        visited: &mut BTreeSet<String>,
+        style: &Style,
+    ) -> fmt::Result {
+        let maxwidth = style.maxwidth;
+        if (style.format == Format::Html || style.format == Format::Term)
+            && self.name == ROOT
+            && maxwidth[1..].iter().any(|w| *w > 0)
+        {
+            self.fmt_row_separator(fo, connectors, style, &Location::Top)?;
+        }
+        let children_iter = self.children.iter().enumerate();
+        if style.reverse {
+            for (pos, child) in children_iter.clone().rev() {
+                if visited.insert(child.borrow().name.clone()) {
+                    connectors.push(pos + 1 == self.children.len());
+                    child.borrow().fmt_tree(fo, connectors, visited, style)?;
+                    connectors.pop();
+                }
+            }
+        }
+        match style.format {
+            // drawn as a graph by Tree
+            Format::Json | Format::Dot | Format::Mermaid | Format::List => {}
+            Format::Md => {
+                if self.name != "/" {
+                    write!(fo, "# ")?;
+                    if self.status == Status::Completed {
+                        write!(fo, "~")?;
+                    }
+                    writeln!(fo, "{}", self.name)?;
+                    if let Some(keyword) = self.status.keyword() {
+                        writeln!(fo, "- * {}", keyword)?;
+                    }
+                    for owner in &self.owners {
+                        writeln!(fo, "- @ {}", owner)?;
+                    }
+                    if let Some(priority) = self.priority {
+                        writeln!(fo, "- ^ {}", priority)?;
+                    }
+                    if let Some(estimate) = self.estimate {
+                        writeln!(fo, "- = {}", estimate)?;
+                    }
+                    if !self.tags.is_empty() {
+                        writeln!(fo, "- # {}", self.tags.join(" "))?;
+                    }
+                    if let Some(due) = self.due {
+                        writeln!(fo, "- ! due {}", due)?;
+                    }
+                    if let Some(start) = self.start {
+                        writeln!(fo, "- > start {}", start)?;
+                    }
+                    if !self.dependencies.is_empty() {
+                        let normalized = self
+                            .dependencies
+                            .iter()
+                            .map(|x| x.replace("~", ""))
+                            .collect::<Vec<String>>();
+                        writeln!(fo, "- : {}", normalized.join(" "))?;
+                    }
+                    for comt in &self.comment {
+                        writeln!(fo, "- % {}", comt)?;
+                    }
+                    for command in &self.recipe {
+                        writeln!(fo, "- $ {}", command)?;
+                    }
+                    if self.auxilaries.is_empty() {
+                        writeln!(fo)?;
+                    }
+                    for ln in &self.auxilaries {
+                        writeln!(fo, "{}", ln)?;
+                    }
+                }
+            }
+            Format::Term | Format::Html => {
+                self.fmt_table(fo, connectors, style, &style.name(self))?;
+            }
+        }
+        if !style.reverse {
+            for (pos, child) in children_iter {
+                if visited.insert(child.borrow().name.clone()) {
+                    connectors.push(pos + 1 == self.children.len());
+                    child.borrow().fmt_tree(fo, connectors, visited, style)?;
+                    connectors.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the children as the items of a JSON array.
+    pub(crate) fn fmt_json_children(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        depth: usize,
+        visited: &mut BTreeSet<String>,
+    ) -> fmt::Result {
+        let mut first = true;
+        for child in &self.children {
+            if visited.insert(child.borrow().name.clone()) {
+                if !first {
+                    writeln!(fo, ",")?;
+                }
+                first = false;
+                child.borrow().fmt_json(fo, depth + 1, visited)?;
+            }
+        }
+        if !first {
+            writeln!(fo)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the todo as a JSON object, without a newline at the end.
+    fn fmt_json(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        depth: usize,
+        visited: &mut BTreeSet<String>,
+    ) -> fmt::Result {
+        let ind = " ".repeat(depth * 4);
+        let name = self.name.trim_end_matches(ROOT);
+        writeln!(fo, "{}{{", ind)?;
+        writeln!(fo, "{}  \"name\": {},", ind, json_quote(name))?;
+        writeln!(fo, "{}  \"status\": \"{}\",", ind, self.status)?;
+        writeln!(fo, "{}  \"owners\": {},", ind, json_array(&self.owners))?;
+        let fields = [
+            ("priority", self.priority.map(|p| p.to_string())),
+            ("effective_priority", self.effective.map(|p| p.to_string())),
+            ("due", self.due.map(|d| d.to_string())),
+            ("start", self.start.map(|d| d.to_string())),
+            ("estimate", self.estimate.map(|e| e.to_string())),
+        ];
+        for (key, value) in fields {
+            match value {
+                Some(value) => {
+                    writeln!(fo, "{}  \"{}\": \"{}\",", ind, key, value)?
+                }
+                None => writeln!(fo, "{}  \"{}\": null,", ind, key)?,
+            }
+        }
+        writeln!(fo, "{}  \"tags\": {},", ind, json_array(&self.tags))?;
+        writeln!(fo, "{}  \"comments\": {},", ind, json_array(&self.comment))?;
+        writeln!(
+            fo,
+            "{}  \"dependencies\": {},",
+            ind,
+            json_array(&self.dependencies)
+        )?;
+        writeln!(fo, "{}  \"recipe\": {},", ind, json_array(&self.recipe))?;
+        writeln!(
+            fo,
+            "{}  \"auxiliaries\": {},",
+            ind,
+            json_array(&self.auxilaries)
+        )?;
+        writeln!(fo, "{}  \"depth\": {},", ind, depth)?;
+        match self.file.is_empty() {
+            true => writeln!(fo, "{}  \"file\": null,", ind)?,
+            false => {
+                writeln!(fo, "{}  \"file\": {},", ind, json_quote(&self.file))?
+            }
+        }
+        if self.children.is_empty() {
+            writeln!(fo, "{}  \"children\": []", ind)?;
+        } else {
+            writeln!(fo, "{}  \"children\": [", ind)?;
+            self.fmt_json_children(fo, depth, visited)?;
+            writeln!(fo, "{}  ]", ind)?;
+        }
+        write!(fo, "{}}}", ind)
+    }
+
+    fn fmt_connector(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &[bool],
+        style: &Style,
+        name: &str,
+    ) -> fmt::Result {
+        let space = style.space;
+        write!(fo, "{}", style.bol)?;
+        for (pos, cn) in connectors.iter().enumerate() {
+            if *cn {
+                if pos + 1 < connectors.len() {
+                    write!(fo, "{}", space.repeat(4))?;
+                } else if style.reverse {
+                    write!(fo, "{}{}", style.border("┌──"), space)?;
+                } else {
+                    write!(fo, "{}{}", style.border("└──"), space)?;
+                }
+            } else if pos + 1 < connectors.len() {
+                write!(fo, "{}{}", style.border("│"), space.repeat(3))?;
+            } else {
+                write!(fo, "{}{}", style.border("├──"), space)?;
+            }
+        }
+        write!(fo, "{}", name)
+    }
+
+    fn fmt_table(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &[bool],
+        style: &Style,
+        name: &str,
+    ) -> fmt::Result {
+        let (maxwidth, space) = (style.maxwidth, style.space);
+        if maxwidth[1..].iter().all(|w| *w == 0) {
+            self.fmt_connector(fo, connectors, style, name)?;
+            return write!(fo, "{}", style.eol);
+        }
+        self.fmt_connector(fo, connectors, style, name)?;
+        write!(
+            fo,
+            "{}",
+            space
+                .repeat(maxwidth[0] - connectors.len() * 4 - self.name.width())
+        )?;
+        let bar = style.border("│");
+        write!(fo, "{}{}", space, bar)?;
+        for (idx, cell) in self.cells(maxwidth).iter().enumerate() {
+            let width = maxwidth[idx + 1];
+            if width > 0 {
+                let text = cell.replace(' ', space);
+                let text = match idx {
+                    _ if self.name == ROOT => text,
+                    0 => style.paint(|t| &t.owner, &text),
+                    1 if self.effective == Some(Priority::P1) => {
+                        style.paint(|t| &t.priority, &text)
+                    }
+                    _ => text,
+                };
+                write!(
+                    fo,
+                    "{}{}{}{}",
+                    space,
+                    text,
+                    space.repeat(1 + width - cell.width()),
+                    bar
+                )?;
+            }
+        }
+        if maxwidth[COMMENT] > 0 {
+            write!(fo, "{}", space)?;
+        }
+        let location = if style.reverse {
+            if self.name == ROOT {
+                Location::Bottom
+            } else {
+                Location::Mid
+            }
+        } else {
+            let mut last = self.children.is_empty();
+            if last {
+                for b in connectors.iter() {
+                    last = last && *b;
+                    if !last {
+                        break;
+                    }
+                }
+            }
+            if last {
+                Location::Bottom
+            } else {
+                Location::Mid
+            }
+        };
+        match maxwidth[COMMENT] {
+            0 => write!(fo, "{}", style.eol)?,
+            _ => self.fmt_comment(fo, connectors, style, &location)?,
+        }
+        self.fmt_row_separator(fo, connectors, style, &location)
+    }
+
+    fn fmt_space_before_table(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &[bool],
+        style: &Style,
+        location: &Location,
+    ) -> fmt::Result {
+        let (maxwidth, space) = (style.maxwidth, style.space);
+        write!(fo, "{}", style.bol)?;
+        for (i, b) in connectors.iter().enumerate() {
+            if style.reverse && i + 1 == connectors.len() {
+                break;
+            }
+            if *b {
+                write!(fo, "{}", space)?;
+            } else {
+                write!(fo, "{}", style.border("│"))?;
+            }
+            write!(fo, "{}", space.repeat(3))?;
+        }
+        if style.reverse {
+            if !connectors.is_empty() {
+                write!(fo, "{}{}", style.border("│"), space.repeat(4))?
+            } else {
+                write!(fo, "{}", space)?
+            }
+        } else if self.children.is_empty() || *location == Location::Top {
+            write!(fo, "{}", space)?;
+        } else {
+            write!(fo, "{}", style.border("│"))?;
+        }
+        write!(
+            fo,
+            "{}",
+            space.repeat(maxwidth[0] - 1 - connectors.len() * 4)
+        )
+    }
+
+    fn fmt_row_separator(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &[bool],
+        style: &Style,
+        location: &Location,
+    ) -> fmt::Result {
+        let (maxwidth, space) = (style.maxwidth, style.space);
+        self.fmt_space_before_table(fo, connectors, style, location)?;
+        let (cl, cm, cr) = match location {
+            Location::Top => ("┌", "┬", "┐"),
+            Location::Mid => ("├", "┼", "┤"),
+            Location::Bottom => ("└", "┴", "┘"),
+        };
+        let lines: Vec<String> = maxwidth[1..]
+            .iter()
+            .filter(|w| **w > 0)
+            .map(|w| "─".repeat(w + 2))
+            .collect();
+        let line = format!("{}{}{}", cl, lines.join(cm), cr);
+        write!(fo, "{}{}{}", space, style.border(&line), style.eol)
+    }
+
+    fn fmt_cont_comment(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &[bool],
+        style: &Style,
+        location: &Location,
+    ) -> fmt::Result {
+        let (maxwidth, space) = (style.maxwidth, style.space);
+        self.fmt_space_before_table(fo, connectors, style, location)?;
+        let bar = style.border("│");
+        write!(fo, "{}{}{}", space, bar, space)?;
+        for width in maxwidth[1..COMMENT].iter().filter(|w| **w > 0) {
+            write!(fo, "{}{}{}{}", space.repeat(*width), space, bar, space)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_comment(
+        &self,
+        fo: &mut fmt::Formatter<'_>,
+        connectors: &[bool],
+        style: &Style,
+        location: &Location,
+    ) -> fmt::Result {
+        let (maxwidth, space) = (style.maxwidth, style.space);
+        let comt = match self.comment.len() {
+            0 => &vec![String::new(); 1],
+            _ => &self.comment,
+        };
+        let mut non_empty_line_cnt = 0;
+        for line in comt {
+            if !line.is_empty() {
+                non_empty_line_cnt += 1;
+            }
+        }
+        let dgt_width = non_empty_line_cnt.to_string().len();
+        let seq_width = match self.comment.len() {
+            0 | 1 => 0,
+            _ => dgt_width + 2,
+        };
+        let cmt_width = maxwidth[COMMENT] - seq_width;
+        let mut empty_line_count = 0;
+        for (idx, line) in comt.iter().enumerate() {
+            if line.is_empty() {
+                empty_line_count += 1;
+            }
+            if idx > 0 {
+                self.fmt_cont_comment(fo, connectors, style, location)?;
+            }
+            let mut start = 0;
+            loop {
+                if seq_width > 0 {
+                    if start == 0 && !line.is_empty() {
+                        write!(
+                            fo,
+                            "{:0>dgt_width$}.{}",
+                            idx + 1 - empty_line_count,
+                            space
+                        )?;
+                    } else {
+                        write!(fo, "{}", space.repeat(seq_width))?;
+                    }
+                }
+                let mut column = 0;
+                let mut text = String::new();
+                for cr in line.chars().skip(start) {
+                    let cw = cr.width().unwrap_or(0);
+                    if column + cw > cmt_width {
+                        break;
+                    }
+                    let htmlcr =
+                        if cr == ' ' { space } else { &cr.to_string() };
+                    text.push_str(htmlcr);
+                    column += cw;
+                    start += 1;
+                    if column == cmt_width {
+                        break;
+                    }
+                }
+                if self.name != ROOT {
+                    text = style.paint(|t| &t.comment, &text);
+                }
+                write!(fo, "{}", text)?;
+                write!(fo, "{}", space.repeat(cmt_width - column))?;
+                write!(fo, "{}{}{}", space, style.border("│"), style.eol)?;
+                if start >= line.chars().count() {
+                    break;
+                }
+                self.fmt_cont_comment(fo, connectors, style, location)?;
+            }
+        }
+        Ok(())
+    }
+}
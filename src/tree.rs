@@ -0,0 +1,1824 @@
+use super::{
+    Date, Estimate, Format, HTMLP, InputFormat, JSON_VERSION, Order, Position,
+    Priority, ROOT, Status, Theme, TodoError, date::date_text, json_array,
+    json_quote, todo::COLUMNS, todo::Style, todo::Todo,
+};
+use libc::{STDOUT_FILENO, TIOCGWINSZ, ioctl, winsize};
+use serde_json::Value;
+use std::cell::{Ref, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
+
+/// Options controlling how a tree is read, filtered and rendered
+#[derive(Clone)]
+pub struct TreeOptions {
+    /// markdown files to read todos from
+    pub(crate) inputs: Vec<String>,
+    /// format of the inputs, None to tell it from the file extension
+    pub(crate) input_format: Option<InputFormat>,
+    /// show only todos owned by these owners
+    pub(crate) owners: Vec<String>,
+    /// show only these todos and their dependencies
+    pub(crate) targets: Vec<String>,
+    /// terminal width, 0 to detect it
+    pub(crate) term_width: usize,
+    /// output format
+    pub(crate) format: Format,
+    /// hide completed todos
+    pub(crate) hide_done: bool,
+    /// tree depth limit, negative to hide leaf nodes
+    pub(crate) depth: i32,
+    /// a separator joining multiple lines of comments
+    pub(crate) separator: String,
+    /// no color
+    pub(crate) no_color: bool,
+    /// colors of the statuses and the table parts
+    pub(crate) theme: Theme,
+    /// use 24-bit colors in a terminal, instead of 256 colors
+    pub(crate) truecolor: bool,
+    /// add todos that are defined in dependencies only
+    pub(crate) auto_add: bool,
+    /// hide comment column
+    pub(crate) hide_comment: bool,
+    /// hide owner column
+    pub(crate) hide_owner: bool,
+    /// reverse
+    pub(crate) reverse: bool,
+    /// sort todos that are dependencies of the same todo
+    pub(crate) sort: bool,
+    /// the date that due and start dates are compared with, None for today
+    pub(crate) today: Option<Date>,
+    /// show only todos at or above this effective priority
+    pub(crate) priority: Option<Priority>,
+    /// order todos that are dependencies of the same todo by priority
+    pub(crate) by_priority: bool,
+    /// show only todos with any of these tags
+    pub(crate) tags: Vec<String>,
+    /// hide todos with any of these tags
+    pub(crate) exclude_tags: Vec<String>,
+    /// highlight the longest chain of uncompleted todos from each target
+    pub(crate) critical_path: bool,
+    /// order of the 'list' format, None for the tree order
+    pub(crate) order: Option<Order>,
+    /// show the todos depending on this one, instead of its dependencies
+    pub(crate) upstream: Option<String>,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        TreeOptions {
+            inputs: Vec::new(),
+            input_format: None,
+            owners: Vec::new(),
+            targets: Vec::new(),
+            term_width: 0,
+            format: Format::Term,
+            hide_done: false,
+            depth: 0,
+            separator: "\n".to_string(),
+            no_color: false,
+            theme: Theme::default(),
+            truecolor: false,
+            auto_add: false,
+            hide_comment: false,
+            hide_owner: false,
+            reverse: false,
+            sort: false,
+            today: None,
+            priority: None,
+            by_priority: false,
+            tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            critical_path: false,
+            order: None,
+            upstream: None,
+        }
+    }
+}
+
+impl TreeOptions {
+    /// Creates options with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a markdown file to read todos from.
+    pub fn input(mut self, path: impl Into<String>) -> Self {
+        self.inputs.push(path.into());
+        self
+    }
+
+    /// Sets the markdown files to read todos from.
+    pub fn inputs(mut self, paths: Vec<String>) -> Self {
+        self.inputs = paths;
+        self
+    }
+
+    /// Sets the format of all inputs, instead of telling it from the file
+    /// extension, '.json' for JSON and anything else for markdown.
+    pub fn input_format(mut self, format: InputFormat) -> Self {
+        self.input_format = Some(format);
+        self
+    }
+
+    /// Shows only todos owned by these owners.
+    pub fn owners(mut self, owners: Vec<String>) -> Self {
+        self.owners = owners;
+        self
+    }
+
+    /// Shows only these todos and their dependencies.
+    pub fn targets(mut self, targets: Vec<String>) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Sets the terminal width, 0 to detect it from stdout.
+    pub fn term_width(mut self, width: usize) -> Self {
+        self.term_width = width;
+        self
+    }
+
+    /// Sets the output format.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Hides completed todos.
+    pub fn hide_done(mut self, yes: bool) -> Self {
+        self.hide_done = yes;
+        self
+    }
+
+    /// Limits tree depth. A negative value hides leaf nodes.
+    pub fn depth(mut self, depth: i32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Joins multi-line comments with this separator.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Disables color output.
+    pub fn no_color(mut self, yes: bool) -> Self {
+        self.no_color = yes;
+        self
+    }
+
+    /// Sets the colors of the statuses and the table parts.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Uses 24-bit colors in a terminal, otherwise the closest of 256 colors.
+    pub fn truecolor(mut self, yes: bool) -> Self {
+        self.truecolor = yes;
+        self
+    }
+
+    /// Auto-adds missing todo definitions from dependencies.
+    pub fn auto_add(mut self, yes: bool) -> Self {
+        self.auto_add = yes;
+        self
+    }
+
+    /// Hides comment column.
+    pub fn hide_comment(mut self, yes: bool) -> Self {
+        self.hide_comment = yes;
+        self
+    }
+
+    /// Hides owner column.
+    pub fn hide_owner(mut self, yes: bool) -> Self {
+        self.hide_owner = yes;
+        self
+    }
+
+    /// Reverses tree order, root at bottom.
+    pub fn reverse(mut self, yes: bool) -> Self {
+        self.reverse = yes;
+        self
+    }
+
+    /// Sorts todos that are dependencies of the same todo.
+    pub fn sort(mut self, yes: bool) -> Self {
+        self.sort = yes;
+        self
+    }
+
+    /// Compares due and start dates with this date instead of today.
+    pub fn today(mut self, date: Date) -> Self {
+        self.today = Some(date);
+        self
+    }
+
+    /// Shows only todos at or above this effective priority.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Orders todos that are dependencies of the same todo by priority.
+    pub fn by_priority(mut self, yes: bool) -> Self {
+        self.by_priority = yes;
+        self
+    }
+
+    /// Shows only todos with any of these tags.
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Hides todos with any of these tags.
+    pub fn exclude_tags(mut self, tags: Vec<String>) -> Self {
+        self.exclude_tags = tags;
+        self
+    }
+
+    /// Highlights the longest chain of uncompleted todos from each target,
+    /// and reports the remaining effort.
+    pub fn critical_path(mut self, yes: bool) -> Self {
+        self.critical_path = yes;
+        self
+    }
+
+    /// Orders the actionable todos of the 'list' format.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Shows the todos depending on this one, directly or not, as a tree
+    /// whose root is this one, instead of the targets and their
+    /// dependencies.
+    pub fn upstream(mut self, name: impl Into<String>) -> Self {
+        self.upstream = Some(name.into());
+        self
+    }
+}
+
+/// An actionable todo and the todos waiting for it
+pub struct Action {
+    todo: Rc<RefCell<Todo>>,
+    /// the number of todos depending on it, directly or not
+    unblocks: usize,
+    /// the todos at the top of the tree depending on it, or itself
+    goals: Vec<String>,
+}
+
+impl Action {
+    /// Returns the todo.
+    pub fn todo(&self) -> Ref<'_, Todo> {
+        self.todo.borrow()
+    }
+
+    /// Returns the number of todos depending on it, directly or not.
+    pub fn unblocks(&self) -> usize {
+        self.unblocks
+    }
+
+    /// Returns the todos at the top of the tree depending on it, or itself
+    /// if it is one of them, the todos no todo depends on if upstream.
+    pub fn goals(&self) -> &[String] {
+        &self.goals
+    }
+}
+
+/// Uncompleted todos and the sum of their estimates
+#[derive(Clone, Default, Debug)]
+pub struct Effort {
+    /// the todo names, a chain from a target down for a critical path
+    todos: Vec<String>,
+    estimate: Estimate,
+    /// the number of todos without estimate
+    unestimated: usize,
+}
+
+impl Effort {
+    /// Returns the todo names.
+    pub fn todos(&self) -> &[String] {
+        &self.todos
+    }
+
+    /// Returns the sum of the estimates.
+    pub fn estimate(&self) -> Estimate {
+        self.estimate
+    }
+
+    /// Returns the number of todos without estimate.
+    pub fn unestimated(&self) -> usize {
+        self.unestimated
+    }
+
+    /// Adds a todo, in front of the others.
+    fn push_front(&mut self, todo: &Todo) {
+        self.todos.insert(0, todo.name.clone());
+        self.add(todo);
+    }
+
+    fn add(&mut self, todo: &Todo) {
+        match todo.estimate {
+            Some(estimate) => self.estimate = self.estimate + estimate,
+            None => self.unestimated += 1,
+        }
+    }
+
+    /// Returns what makes a chain longer, the estimates, then the count.
+    fn length(&self) -> (Estimate, usize) {
+        (self.estimate, self.todos.len())
+    }
+}
+
+impl fmt::Display for Effort {
+    /// Writes such as '2d 4h, 3 todos, 1 without estimate'.
+    fn fmt(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.todos.len();
+        let estimated = self.estimate != Estimate::default();
+        if estimated {
+            write!(fo, "{}, ", self.estimate)?;
+        }
+        write!(fo, "{} todo{}", count, if count == 1 { "" } else { "s" })?;
+        if estimated && self.unestimated > 0 {
+            write!(fo, ", {} without estimate", self.unestimated)?;
+        }
+        Ok(())
+    }
+}
+
+/// A tree of todos
+pub struct Tree {
+    /// tree root
+    root: Rc<RefCell<Todo>>,
+    /// options the tree was built with
+    opts: TreeOptions,
+    /// maximum length of each column
+    maxwidth: [usize; COLUMNS],
+    /// auxilary lines before the first todo
+    auxilaries: Vec<String>,
+    /// the members of each team, from the '# @team' lines
+    teams: BTreeMap<String, Vec<String>>,
+    /// the errors found so far, if all of them are being checked
+    diagnostics: Option<Vec<TodoError>>,
+    /// the critical path of each target, if '--critical-path' is given
+    critical: Vec<Effort>,
+    /// all the uncompleted todos of the targets
+    remaining: Effort,
+}
+
+impl fmt::Display for Tree {
+    fn fmt(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.opts.format == Format::Dot {
+            return self.fmt_dot(fo);
+        } else if self.opts.format == Format::List {
+            return self.fmt_list(fo);
+        } else if self.opts.format == Format::Mermaid {
+            return self.fmt_mermaid(fo);
+        } else if self.opts.format == Format::Json {
+            return self.fmt_json(fo);
+        }
+        if self.opts.format == Format::Md {
+            for ln in &self.auxilaries {
+                writeln!(fo, "{}", ln)?;
+            }
+            for (team, members) in &self.teams {
+                writeln!(fo, "# @{}", team)?;
+                for member in members {
+                    writeln!(fo, "- @ {}", member)?;
+                }
+                writeln!(fo)?;
+            }
+        } else if self.opts.format == Format::Html {
+            writeln!(fo, "<html><head><meta charset='utf-8'></head><body>")?;
+        }
+        let mut connectors: Vec<bool> = Vec::new();
+        let mut visited: BTreeSet<String> = BTreeSet::new();
+        let style = Style::new(&self.maxwidth, &self.opts);
+        self.root.borrow().fmt_tree(
+            fo,
+            &mut connectors,
+            &mut visited,
+            &style,
+        )?;
+        if self.opts.critical_path
+            && matches!(self.opts.format, Format::Term | Format::Html)
+        {
+            self.fmt_critical(fo)?;
+        }
+        if self.opts.format == Format::Html {
+            writeln!(fo, "</body></html>")?;
+        }
+        Ok(())
+    }
+}
+
+impl Tree {
+    /// Creates a tree from the markdown files in options.
+    pub fn new(opts: &TreeOptions) -> Result<Self, TodoError> {
+        let mut tree = Tree::empty(opts)?;
+        tree.load()?;
+        Ok(tree)
+    }
+
+    /// Checks the inputs, returning all their problems instead of the first.
+    ///
+    /// The targets, the screen width, '--reverse' and the output format are
+    /// ignored.
+    pub fn check(opts: &TreeOptions) -> Vec<TodoError> {
+        let opts = opts
+            .clone()
+            .targets(Vec::new())
+            .format(Format::Md)
+            .reverse(false)
+            .term_width(usize::MAX);
+        let mut tree = match Tree::empty(&opts) {
+            Ok(tree) => tree,
+            Err(e) => return vec![e],
+        };
+        tree.diagnostics = Some(Vec::new());
+        let rtn = tree.load();
+        let mut diagnostics = tree.diagnostics.unwrap_or_default();
+        if let Err(e) = rtn {
+            diagnostics.push(e);
+        }
+        diagnostics.sort_by_key(|e| match e {
+            TodoError::Located(pos, _) => (
+                opts.inputs.iter().position(|f| *f == pos.file),
+                pos.line,
+                pos.column,
+            ),
+            _ => (Some(usize::MAX), 0, 0),
+        });
+        diagnostics
+    }
+
+    /// Creates a tree without any todo.
+    fn empty(opts: &TreeOptions) -> Result<Self, TodoError> {
+        if opts.reverse
+            && opts.format != Format::Term
+            && opts.format != Format::Html
+        {
+            return Err(TodoError::Reverse);
+        }
+        Ok(Tree {
+            root: Rc::new(RefCell::new(Todo::new(
+                ROOT.to_string(),
+                Status::Pending,
+                Vec::new(),
+                Vec::new(),
+                opts.targets.to_vec(),
+                Vec::new(),
+            )?)),
+            opts: opts.clone(),
+            maxwidth: [0; COLUMNS],
+            auxilaries: Vec::new(),
+            teams: BTreeMap::new(),
+            diagnostics: None,
+            critical: Vec::new(),
+            remaining: Effort::default(),
+        })
+    }
+
+    /// Reads the inputs, and builds the tree from them.
+    fn load(&mut self) -> Result<(), TodoError> {
+        self.opts.today = self.opts.today.or_else(|| Some(Date::today()));
+        if self.opts.format == Format::Json {
+            // the json output is read back as the same todos, hiding none
+            self.opts = self
+                .opts
+                .clone()
+                .owners(Vec::new())
+                .tags(Vec::new())
+                .exclude_tags(Vec::new())
+                .hide_done(false)
+                .hide_comment(false)
+                .hide_owner(false)
+                .depth(0);
+            self.opts.priority = None;
+            self.opts.upstream = None;
+        }
+        let opts = self.opts.clone();
+        let mut screen_width: usize = 80;
+        if opts.term_width > 0 {
+            screen_width = opts.term_width;
+        } else if opts.format == Format::Term {
+            let mut ws = winsize {
+                ws_row: 0,
+                ws_col: 0,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            unsafe {
+                if ioctl(STDOUT_FILENO, TIOCGWINSZ, &mut ws) != -1
+                    && ws.ws_col > 0
+                {
+                    screen_width = ws.ws_col as usize;
+                }
+            }
+        };
+        let mut dict = BTreeMap::new();
+        let mut list: Vec<String> = Vec::new();
+        for mdfile in &opts.inputs {
+            let suffix = match opts.inputs.len() {
+                1 => "",
+                _ => &format!(
+                    "@{}",
+                    Path::new(mdfile).file_stem().unwrap().to_string_lossy()
+                ),
+            };
+            let is_json = match opts.input_format {
+                Some(fmt) => fmt == InputFormat::Json,
+                None => mdfile.ends_with(".json"),
+            };
+            if is_json {
+                self.readjson(suffix, mdfile, &mut dict, &mut list)?;
+            } else {
+                self.readmd(suffix, mdfile, &mut dict, &mut list)?;
+            }
+        }
+        // check dict
+        if dict.is_empty() {
+            return Err(TodoError::NoTodo);
+        }
+        let cycles = find_cycles(&dict, &list);
+        // add all TODOs that have no parent to ROOT's dependencies
+        if self.root.borrow().dependencies.is_empty() {
+            let mut noparent: BTreeSet<String> = dict.keys().cloned().collect();
+            for todo in dict.values() {
+                for dep in &todo.borrow().dependencies {
+                    let dep = dep.replace("~", "");
+                    noparent.remove(&dep);
+                }
+                if noparent.is_empty() {
+                    self.report(TodoError::NoRoot {
+                        cycles: cycles.clone(),
+                    })?;
+                    break;
+                }
+            }
+            if opts.sort {
+                list.sort();
+            }
+            for nm in &list {
+                if noparent.contains(nm) {
+                    self.root.borrow_mut().dependencies.push(nm.clone());
+                }
+            }
+        }
+        self.check_todos_in_dep_only(opts.auto_add, &mut dict)?;
+        if self.diagnostics.is_some() {
+            self.validate(&dict, &list)?;
+        }
+        for cycle in cycles {
+            let last = dict[&cycle[cycle.len() - 1]].borrow();
+            let position = last.dep_position(&cycle[0]).clone();
+            drop(last);
+            self.report(TodoError::Loop { cycle }.at(&position))?;
+        }
+        if self.diagnostics.as_ref().is_some_and(|d| !d.is_empty()) {
+            return Ok(());
+        }
+        spread_priorities(&dict);
+        if let Some(name) = &opts.upstream {
+            dict = self.upstream(&dict, &list, name)?;
+        }
+        if opts.critical_path {
+            self.find_critical_paths(&dict);
+        }
+        let mut owners: BTreeMap<String, (BTreeSet<String>, bool)> = opts
+            .owners
+            .iter()
+            .map(|s| (s.to_string(), (self.members(s), false)))
+            .collect();
+        let mut path: Vec<String> = Vec::new();
+        let mut visited: BTreeSet<String> = BTreeSet::new();
+        self.root.borrow_mut().build_tree(
+            &mut visited,
+            &dict,
+            &mut path,
+            0,
+            &opts,
+            &mut owners,
+        )?;
+        self.root.borrow_mut().get_maxwidth(
+            &mut self.maxwidth,
+            0,
+            screen_width,
+        )?;
+        for (owner, (_, used)) in owners.iter() {
+            if !*used {
+                self.report(TodoError::NoSuchOwner {
+                    owner: owner.to_string(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an owner and, if it is a team, all its members.
+    fn members(&self, owner: &str) -> BTreeSet<String> {
+        let mut members = BTreeSet::new();
+        let mut stack = vec![owner.trim_start_matches('@').to_string()];
+        while let Some(owner) = stack.pop() {
+            if let Some(team) = self.teams.get(&owner)
+                && !members.contains(&owner)
+            {
+                stack.extend(team.iter().cloned());
+            }
+            members.insert(owner);
+        }
+        members
+    }
+
+    /// Returns an error, or keeps it if all errors are being checked.
+    fn report(&mut self, err: TodoError) -> Result<(), TodoError> {
+        match &mut self.diagnostics {
+            Some(diagnostics) => {
+                diagnostics.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    /// Reports the missing and not completed dependencies of all todos,
+    /// build_tree stops at the first one.
+    fn validate(
+        &mut self,
+        dict: &BTreeMap<String, Rc<RefCell<Todo>>>,
+        list: &[String],
+    ) -> Result<(), TodoError> {
+        for nm in list {
+            let todo = dict[nm].borrow();
+            let mut notdonedeps: Vec<String> = Vec::new();
+            for dep in &todo.dependencies {
+                let dep = dep.replace("~", "");
+                match dict.get(&dep) {
+                    None => self.report(
+                        TodoError::Missing {
+                            name: dep.to_string(),
+                        }
+                        .at(todo.dep_position(&dep)),
+                    )?,
+                    Some(child) if !child.borrow().status.is_done() => {
+                        notdonedeps.push(dep)
+                    }
+                    _ => {}
+                }
+            }
+            if todo.status == Status::Completed && !notdonedeps.is_empty() {
+                self.report(
+                    TodoError::Incomplete {
+                        name: todo.name.clone(),
+                        dependencies: notdonedeps,
+                    }
+                    .at(&todo.position),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns copies of a todo and of the todos depending on it, directly
+    /// or not, the children of each one being the todos depending on it, and
+    /// makes the first one the only target.
+    ///
+    /// The statuses are computed from the dependencies first, as usual.
+    fn upstream(
+        &mut self,
+        dict: &BTreeMap<String, Rc<RefCell<Todo>>>,
+        list: &[String],
+        name: &str,
+    ) -> Result<BTreeMap<String, Rc<RefCell<Todo>>>, TodoError> {
+        if !dict.contains_key(name) {
+            return Err(TodoError::Missing {
+                name: name.to_string(),
+            });
+        }
+        let mut all = Todo::new(
+            ROOT.to_string(),
+            Status::Pending,
+            Vec::new(),
+            Vec::new(),
+            dict.keys().cloned().collect(),
+            Vec::new(),
+        )?;
+        let mut opts = TreeOptions::new();
+        opts.today = self.opts.today;
+        all.build_tree(
+            &mut BTreeSet::new(),
+            dict,
+            &mut Vec::new(),
+            0,
+            &opts,
+            &mut BTreeMap::new(),
+        )?;
+        let mut dependents: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for nm in list {
+            for dep in &dict[nm].borrow().dependencies {
+                let dep = dep.replace("~", "");
+                dependents.entry(dep).or_default().push(nm.clone());
+            }
+        }
+        if self.opts.sort {
+            dependents.values_mut().for_each(|d| d.sort());
+        }
+        let mut upstream = BTreeMap::new();
+        let mut stack = vec![name.to_string()];
+        while let Some(nm) = stack.pop() {
+            if upstream.contains_key(&nm) {
+                continue;
+            }
+            let above = dependents.remove(&nm).unwrap_or_default();
+            stack.extend(above.iter().cloned());
+            let todo = dict[&nm].borrow().upstream(above);
+            upstream.insert(nm, Rc::new(RefCell::new(todo)));
+        }
+        self.root.borrow_mut().dependencies = vec![name.to_string()];
+        Ok(upstream)
+    }
+
+    /// Finds the longest chain of uncompleted todos from each target, and
+    /// marks its todos critical.
+    ///
+    /// A chain is longer if its estimates sum up to more, or if it has more
+    /// todos, so the todos without estimate are counted.
+    fn find_critical_paths(
+        &mut self,
+        dict: &BTreeMap<String, Rc<RefCell<Todo>>>,
+    ) {
+        fn longest(
+            dict: &BTreeMap<String, Rc<RefCell<Todo>>>,
+            name: &str,
+            chains: &mut BTreeMap<String, Effort>,
+        ) -> Option<Effort> {
+            if let Some(chain) = chains.get(name) {
+                return Some(chain.clone());
+            }
+            let todo = dict.get(name)?.borrow();
+            if todo.status.is_done() {
+                return None;
+            }
+            let mut chain = Effort::default();
+            for dep in &todo.dependencies {
+                if let Some(below) =
+                    longest(dict, &dep.replace("~", ""), chains)
+                    && below.length() > chain.length()
+                {
+                    chain = below;
+                }
+            }
+            chain.push_front(&todo);
+            chains.insert(name.to_string(), chain.clone());
+            Some(chain)
+        }
+        let mut chains = BTreeMap::new();
+        let targets = self.root.borrow().dependencies.clone();
+        for target in &targets {
+            if let Some(chain) =
+                longest(dict, &target.replace("~", ""), &mut chains)
+            {
+                for name in &chain.todos {
+                    dict[name].borrow_mut().critical = true;
+                }
+                self.critical.push(chain);
+            }
+        }
+        let mut stack: Vec<String> =
+            targets.iter().rev().map(|t| t.replace("~", "")).collect();
+        let mut visited = BTreeSet::new();
+        while let Some(name) = stack.pop() {
+            let Some(todo) = dict.get(&name) else {
+                continue;
+            };
+            let todo = todo.borrow();
+            if todo.status.is_done() || !visited.insert(name.clone()) {
+                continue;
+            }
+            self.remaining.todos.push(name);
+            self.remaining.add(&todo);
+            stack.extend(
+                todo.dependencies.iter().rev().map(|d| d.replace("~", "")),
+            );
+        }
+    }
+
+    /// Returns the critical path of each target, see '--critical-path'.
+    pub fn critical_paths(&self) -> &[Effort] {
+        &self.critical
+    }
+
+    /// Returns all the uncompleted todos of the targets, and their effort,
+    /// see '--critical-path'.
+    pub fn remaining(&self) -> &Effort {
+        &self.remaining
+    }
+
+    /// Writes the critical paths and the remaining effort below the tree.
+    fn fmt_critical(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (bol, eol) = match self.opts.format {
+            Format::Html => (HTMLP, "</p>\n"),
+            _ => ("", "\n"),
+        };
+        for chain in &self.critical {
+            let path = chain.todos.join(" -> ");
+            write!(fo, "{}Critical path: {}, {}{}", bol, path, chain, eol)?;
+        }
+        write!(fo, "{}Remaining effort: {}{}", bol, self.remaining, eol)
+    }
+
+    /// Returns the options the tree was built with.
+    pub fn options(&self) -> &TreeOptions {
+        &self.opts
+    }
+
+    /// Returns the root todo, named '/', whose children are the top todos.
+    pub fn root(&self) -> Ref<'_, Todo> {
+        self.root.borrow()
+    }
+
+    /// Returns the todos shown in the tree, in tree order, each once.
+    pub fn todos(&self) -> Vec<Rc<RefCell<Todo>>> {
+        let mut todos = Vec::new();
+        let mut visited: BTreeSet<String> = BTreeSet::new();
+        self.root.borrow().collect(&mut todos, &mut visited);
+        todos
+    }
+
+    /// Returns the dependency edges between the todos, as their indexes.
+    ///
+    /// Unlike the tree, a todo shared by several todos has an edge from each
+    /// of them.
+    fn edges(&self, todos: &[Rc<RefCell<Todo>>]) -> Vec<(usize, usize)> {
+        // the name used in dependencies, without the '/' of a cut todo
+        let index: BTreeMap<String, usize> = todos
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                (t.borrow().name.trim_end_matches(ROOT).to_string(), i)
+            })
+            .collect();
+        let mut edges = Vec::new();
+        for (i, todo) in todos.iter().enumerate() {
+            for dep in &todo.borrow().dependencies {
+                if let Some(j) = index.get(&dep.replace("~", "")) {
+                    edges.push((i, *j));
+                }
+            }
+        }
+        edges
+    }
+
+    /// Writes the tree as a JSON document, see todotree.schema.json
+    fn fmt_json(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(fo, "{{")?;
+        writeln!(fo, "  \"version\": {},", JSON_VERSION)?;
+        writeln!(fo, "  \"auxiliaries\": {},", json_array(&self.auxilaries))?;
+        write!(fo, "  \"teams\": {{")?;
+        for (idx, (team, members)) in self.teams.iter().enumerate() {
+            let sep = if idx == 0 { "" } else { "," };
+            write!(
+                fo,
+                "{}\n    {}: {}",
+                sep,
+                json_quote(team),
+                json_array(members)
+            )?;
+        }
+        match self.teams.is_empty() {
+            true => writeln!(fo, "}},")?,
+            false => writeln!(fo, "\n  }},")?,
+        }
+        let mut visited: BTreeSet<String> = BTreeSet::new();
+        let root = self.root.borrow();
+        if root.children.is_empty() {
+            writeln!(fo, "  \"todos\": []")?;
+        } else {
+            writeln!(fo, "  \"todos\": [")?;
+            root.fmt_json_children(fo, 0, &mut visited)?;
+            writeln!(fo, "  ]")?;
+        }
+        writeln!(fo, "}}")
+    }
+
+    /// Returns the actionable todos shown in the tree, in tree order or in
+    /// the order of the options.
+    ///
+    /// A todo shown only because a todo it depends on is owned or tagged as
+    /// filtered is left out.
+    pub fn actions(&self) -> Vec<Action> {
+        let todos = self.todos();
+        let mut parents = vec![Vec::new(); todos.len()];
+        for (i, j) in self.edges(&todos) {
+            parents[j].push(i);
+        }
+        // the todos at the top of the tree, or of the graph if upstream
+        let top: BTreeSet<String> = match self.opts.upstream {
+            Some(_) => todos
+                .iter()
+                .filter(|t| t.borrow().dependents.is_empty())
+                .map(|t| t.borrow().name.clone())
+                .collect(),
+            None => self
+                .root
+                .borrow()
+                .children
+                .iter()
+                .map(|t| t.borrow().name.clone())
+                .collect(),
+        };
+        let mut actions = Vec::new();
+        for (idx, todo) in todos.iter().enumerate() {
+            let t = todo.borrow();
+            if t.status != Status::Actionable || !t.matched {
+                continue;
+            }
+            let mut above = BTreeSet::from([idx]);
+            let mut stack = vec![idx];
+            while let Some(i) = stack.pop() {
+                for p in &parents[i] {
+                    if above.insert(*p) {
+                        stack.push(*p);
+                    }
+                }
+            }
+            let goals = above
+                .iter()
+                .map(|i| todos[*i].borrow().name.clone())
+                .filter(|name| top.contains(name))
+                .map(|name| name.trim_end_matches(ROOT).to_string())
+                .collect();
+            actions.push(Action {
+                todo: Rc::clone(todo),
+                unblocks: above.len() - 1,
+                goals,
+            });
+        }
+        match self.opts.order {
+            Some(Order::Priority) => actions.sort_by_key(|a| {
+                let effective = a.todo().effective;
+                (effective.is_none(), effective)
+            }),
+            Some(Order::Due) => actions.sort_by_key(|a| {
+                let due = a.todo().due;
+                (due.is_none(), due)
+            }),
+            Some(Order::Unblocks) => {
+                actions.sort_by_key(|a| std::cmp::Reverse(a.unblocks))
+            }
+            None => {}
+        }
+        actions
+    }
+
+    /// Writes the actionable todos, one per line, with the columns that are
+    /// not empty.
+    fn fmt_list(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows = vec![
+            [
+                "TODO", "OWNER", "PRIO", "DUE", "UNBLOCKS", "GOALS", "COMMENT",
+            ]
+            .map(String::from),
+        ];
+        for action in self.actions() {
+            let todo = action.todo();
+            rows.push([
+                todo.name.trim_end_matches(ROOT).to_string(),
+                todo.owners.join(", "),
+                todo.effective.map(|p| p.to_string()).unwrap_or_default(),
+                todo.due.map(|d| d.to_string()).unwrap_or_default(),
+                action.unblocks.to_string(),
+                action.goals.join(", "),
+                todo.comment.join(" "),
+            ]);
+        }
+        if rows.len() == 1 {
+            return Ok(());
+        }
+        let columns: Vec<usize> = (0..rows[0].len())
+            .filter(|c| rows[1..].iter().any(|r| !r[*c].is_empty()))
+            .collect();
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|c| rows.iter().map(|r| r[*c].width()).max().unwrap_or(0))
+            .collect();
+        for row in &rows {
+            let mut line = String::new();
+            for (c, width) in columns.iter().zip(&widths) {
+                line.push_str(&row[*c]);
+                line.push_str(&" ".repeat(width + 2 - row[*c].width()));
+            }
+            writeln!(fo, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the tree as a Graphviz digraph.
+    fn fmt_dot(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn quote(s: &str) -> String {
+            s.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+        let todos = self.todos();
+        writeln!(fo, "digraph todotree {{")?;
+        writeln!(fo, "    node [shape=box];")?;
+        for todo in &todos {
+            let todo = todo.borrow();
+            let mut label = quote(&todo.name);
+            if !todo.owners.is_empty() {
+                label.push_str("\\n");
+                label.push_str(&quote(&todo.owners.join(", ")));
+            }
+            let mut color = String::new();
+            if !self.opts.no_color {
+                let paint = self.opts.theme.status(todo.status);
+                if let Some(c) = paint.color {
+                    let c = match c.css() {
+                        c if c.starts_with('#') => format!("\"{}\"", c),
+                        c => c,
+                    };
+                    color = format!(", color={}, fontcolor={}", c, c);
+                }
+                if paint.strike {
+                    color.push_str(", style=dashed");
+                }
+            }
+            writeln!(
+                fo,
+                "    \"{}\" [label=\"{}\"{}];",
+                quote(&todo.name),
+                label,
+                color
+            )?;
+        }
+        for (i, j) in self.edges(&todos) {
+            writeln!(
+                fo,
+                "    \"{}\" -> \"{}\";",
+                quote(&todos[i].borrow().name),
+                quote(&todos[j].borrow().name)
+            )?;
+        }
+        writeln!(fo, "}}")
+    }
+
+    /// Writes the tree as a Mermaid flowchart.
+    ///
+    /// Nodes are numbered, as a todo name may contain Mermaid syntax.
+    fn fmt_mermaid(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn quote(s: &str) -> String {
+            let mut quoted = String::new();
+            for c in s.chars() {
+                match c {
+                    '#' => quoted.push_str("#35;"),
+                    '"' => quoted.push_str("#quot;"),
+                    '<' => quoted.push_str("#lt;"),
+                    '>' => quoted.push_str("#gt;"),
+                    _ => quoted.push(c),
+                }
+            }
+            quoted
+        }
+        let todos = self.todos();
+        writeln!(fo, "flowchart TD")?;
+        if !self.opts.no_color {
+            for status in [
+                Status::Completed,
+                Status::Actionable,
+                Status::Pending,
+                Status::InProgress,
+                Status::Blocked,
+                Status::Cancelled,
+                Status::Waiting,
+            ] {
+                let paint = self.opts.theme.status(status);
+                let color = match paint.color {
+                    Some(c) => c.css(),
+                    None => "black".to_string(),
+                };
+                write!(
+                    fo,
+                    "    classDef {} color:{},stroke:{}",
+                    status, color, color
+                )?;
+                if paint.strike {
+                    write!(fo, ",text-decoration:line-through")?;
+                }
+                writeln!(fo)?;
+            }
+        }
+        for (i, todo) in todos.iter().enumerate() {
+            let todo = todo.borrow();
+            let mut label = quote(&todo.name);
+            if !todo.owners.is_empty() {
+                label.push_str("<br/>@ ");
+                label.push_str(&quote(&todo.owners.join(", ")));
+            }
+            for comt in &todo.comment {
+                label.push_str("<br/>");
+                label.push_str(&quote(comt));
+            }
+            write!(fo, "    t{}[\"{}\"]", i, label)?;
+            if !self.opts.no_color {
+                write!(fo, ":::{}", todo.status)?;
+            }
+            writeln!(fo)?;
+        }
+        for (i, j) in self.edges(&todos) {
+            writeln!(fo, "    t{} --> t{}", i, j)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a list of todos from a markdown fie.
+    fn readmd(
+        &mut self,
+        suffix: &str,
+        mdfile: &str,
+        dict: &mut BTreeMap<String, Rc<RefCell<Todo>>>,
+        list: &mut Vec<String>,
+    ) -> Result<(), TodoError> {
+        let mut name = String::new();
+        let mut status = Status::Pending;
+        let mut owners: Vec<String> = Vec::new();
+        let mut comment: Vec<String> = Vec::new();
+        let mut dependencies: Vec<String> = Vec::new();
+        let mut due: Option<Date> = None;
+        let mut start: Option<Date> = None;
+        let mut priority: Option<Priority> = None;
+        let mut estimate: Option<Estimate> = None;
+        let mut tags: Vec<String> = Vec::new();
+        let mut recipe: Vec<String> = Vec::new();
+        let mut auxilaries: Vec<String> = Vec::new();
+        let mut position = Position::default();
+        let mut dep_positions: BTreeMap<String, Position> = BTreeMap::new();
+        for (idx, raw) in read_to_string(mdfile)?.lines().enumerate() {
+            let escaped = escape(raw);
+            let ln = escaped.trim();
+            let here = |token: &str| Position::new(mdfile, idx + 1, raw, token);
+            if ln.starts_with("# ") {
+                let todo = Todo::new(
+                    name,
+                    status,
+                    owners,
+                    comment,
+                    dependencies,
+                    auxilaries,
+                )
+                .map(|t| t.dated(due, start).prioritized(priority).tagged(tags))
+                .map(|t| t.estimated(estimate).scripted(recipe))
+                .map(|t| t.located(position.clone(), dep_positions))
+                .map_err(|e| e.at(&position));
+                self.new_todo_if_any(todo, mdfile, dict, list)?;
+                name = ln.get(2..).map(|x| x.trim().to_string()).ok_or_else(
+                    || TodoError::Heading {
+                        line: ln.to_string(),
+                    },
+                )?;
+                position = here(name.trim_matches('~'));
+                dep_positions = BTreeMap::new();
+                let is_team = name.len() > 1 && name.rfind('@') == Some(0);
+                if name.is_empty() || name == ROOT {
+                    self.report(TodoError::ReservedName.at(&position))?;
+                } else if name.contains('@') && !is_team {
+                    self.report(
+                        TodoError::AtInName {
+                            name: name.to_string(),
+                        }
+                        .at(&position),
+                    )?;
+                }
+                status = if name.starts_with("~") {
+                    name = name.replace("~", "");
+                    Status::Completed
+                } else {
+                    Status::Pending
+                };
+                if !is_team {
+                    name = format!("{}{}", name, suffix);
+                }
+                owners = Vec::new();
+                comment = Vec::new();
+                dependencies = Vec::new();
+                due = None;
+                start = None;
+                priority = None;
+                estimate = None;
+                tags = Vec::new();
+                recipe = Vec::new();
+                auxilaries = Vec::new();
+                continue;
+            }
+            let is_field = ln.starts_with("- @ ")
+                || ln.starts_with("- % ")
+                || ln == "- %"
+                || ln.starts_with("- : ")
+                || ln.starts_with("- ! ")
+                || ln.starts_with("- > ")
+                || ln.starts_with("- ^ ")
+                || ln.starts_with("- = ")
+                || ln.starts_with("- # ")
+                || ln.starts_with("- * ")
+                || ln.starts_with("- $ ");
+            if is_field && name.is_empty() && position.line == 0 {
+                position = here("");
+            }
+            if ln.starts_with("- @ ") {
+                for owner in ln.get(3..).unwrap().split(',') {
+                    let owner = owner.trim().trim_start_matches('@');
+                    if owner.is_empty() {
+                        continue;
+                    } else if owners.iter().any(|o| o == owner) {
+                        self.report(
+                            TodoError::DuplicatedOwner {
+                                name: name.clone(),
+                                owner: owner.to_string(),
+                            }
+                            .at(&here(owner)),
+                        )?;
+                        continue;
+                    }
+                    owners.push(owner.to_string());
+                }
+            } else if ln.starts_with("- % ") || ln == "- %" {
+                if !self.opts.hide_comment {
+                    comment.push(match ln {
+                        "- %" => String::new(),
+                        _ => ln.get(4..).unwrap().trim_end().to_string(),
+                    });
+                }
+            } else if ln.starts_with("- : ") {
+                for s in ln.get(3..).unwrap().split_whitespace() {
+                    let dep = if s.contains('@') {
+                        s.to_string()
+                    } else {
+                        format!("{s}{suffix}")
+                    };
+                    let nom = dep.replace("~", "");
+                    let pos = here(s.trim_start_matches('~'));
+                    if nom == name {
+                        self.report(
+                            TodoError::SelfDependency {
+                                name: nom.to_string(),
+                            }
+                            .at(&pos),
+                        )?;
+                        continue;
+                    }
+                    dep_positions.entry(nom).or_insert(pos);
+                    dependencies.push(dep);
+                }
+            } else if ln.starts_with("- ! ") || ln.starts_with("- > ") {
+                let (word, date) = match ln.starts_with("- ! ") {
+                    true => ("due", &mut due),
+                    false => ("start", &mut start),
+                };
+                let text = date_text(ln.get(4..).unwrap(), word);
+                if date.is_some() {
+                    self.report(
+                        TodoError::MultipleDates { name: name.clone() }
+                            .at(&here(text)),
+                    )?;
+                    continue;
+                }
+                match text.parse::<Date>() {
+                    Ok(d) => *date = Some(d),
+                    Err(e) => self.report(e.at(&here(text)))?,
+                }
+            } else if ln.starts_with("- # ") {
+                for tag in ln.get(3..).unwrap().split_whitespace() {
+                    let tag = tag.trim_start_matches('+').to_string();
+                    if !tag.is_empty() && !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+            } else if ln.starts_with("- * ") {
+                let text = ln.get(4..).unwrap().trim();
+                if status != Status::Pending {
+                    self.report(
+                        TodoError::MultipleStatuses { name: name.clone() }
+                            .at(&here(text)),
+                    )?;
+                    continue;
+                }
+                match text.parse::<Status>() {
+                    Ok(s) => status = s,
+                    Err(e) => self.report(e.at(&here(text)))?,
+                }
+            } else if ln.starts_with("- $ ") {
+                recipe.push(ln.get(4..).unwrap().trim().to_string());
+            } else if ln.starts_with("- ^ ") {
+                let text = ln.get(4..).unwrap().trim();
+                if priority.is_some() {
+                    self.report(
+                        TodoError::MultiplePriorities { name: name.clone() }
+                            .at(&here(text)),
+                    )?;
+                    continue;
+                }
+                match text.parse::<Priority>() {
+                    Ok(p) => priority = Some(p),
+                    Err(e) => self.report(e.at(&here(text)))?,
+                }
+            } else if ln.starts_with("- = ") {
+                let text = ln.get(4..).unwrap().trim();
+                if estimate.is_some() {
+                    self.report(
+                        TodoError::MultipleEstimates { name: name.clone() }
+                            .at(&here(text)),
+                    )?;
+                    continue;
+                }
+                match text.parse::<Estimate>() {
+                    Ok(e) => estimate = Some(e),
+                    Err(e) => self.report(e.at(&here(text)))?,
+                }
+            } else {
+                auxilaries.push(ln.to_string());
+            }
+        }
+        let todo =
+            Todo::new(name, status, owners, comment, dependencies, auxilaries)
+                .map(|t| t.dated(due, start).prioritized(priority).tagged(tags))
+                .map(|t| t.estimated(estimate).scripted(recipe))
+                .map(|t| t.located(position.clone(), dep_positions))
+                .map_err(|e| e.at(&position));
+        self.new_todo_if_any(todo, mdfile, dict, list)
+    }
+
+    /// Creates a list of todos from a JSON file written by '-f json'.
+    ///
+    /// Auto-added todos are skipped, as they are not in the markdown file.
+    fn readjson(
+        &mut self,
+        suffix: &str,
+        jsonfile: &str,
+        dict: &mut BTreeMap<String, Rc<RefCell<Todo>>>,
+        list: &mut Vec<String>,
+    ) -> Result<(), TodoError> {
+        let invalid = |msg: &str| TodoError::Json {
+            file: jsonfile.to_string(),
+            reason: msg.to_string(),
+        };
+        let text = read_to_string(jsonfile)?;
+        let doc: Value =
+            serde_json::from_str(&text).map_err(|e| invalid(&e.to_string()))?;
+        // version 1 has a single owner, and no teams
+        let version = doc["version"].as_u64().unwrap_or_default();
+        if version == 0 || version > JSON_VERSION as u64 {
+            return Err(invalid(&format!(
+                "'version' should be 1 to {}",
+                JSON_VERSION
+            )));
+        }
+        self.auxilaries = json_strings(&doc["auxiliaries"])
+            .ok_or_else(|| invalid("'auxiliaries' is not a string array"))?;
+        if let Some(teams) = doc["teams"].as_object() {
+            for (team, members) in teams {
+                let members = json_strings(members).ok_or_else(|| {
+                    invalid(&format!("'{}' is not a string array", team))
+                })?;
+                self.teams
+                    .entry(team.to_string())
+                    .or_default()
+                    .extend(members);
+            }
+        }
+        let mut stack: Vec<&Value> = match doc["todos"].as_array() {
+            Some(todos) => todos.iter().rev().collect(),
+            None => return Err(invalid("'todos' is not an array")),
+        };
+        // the todos are in the order of the text, where their names are found
+        let mut cursor = 0;
+        while let Some(obj) = stack.pop() {
+            if let Some(children) = obj["children"].as_array() {
+                stack.extend(children.iter().rev());
+            }
+            if obj["file"].is_null() {
+                continue;
+            }
+            let field = |key: &str| {
+                obj[key].as_str().map(|s| s.to_string()).ok_or_else(|| {
+                    invalid(&format!("'{}' is not a string in {}", key, obj))
+                })
+            };
+            let strings = |key: &str| {
+                json_strings(&obj[key]).ok_or_else(|| {
+                    invalid(&format!("'{}' is not a string array", key))
+                })
+            };
+            let date = |key: &str| match &obj[key] {
+                Value::Null => Ok(None),
+                Value::String(s) => s.parse::<Date>().map(Some),
+                _ => Err(invalid(&format!("'{}' is not a string", key))),
+            };
+            let mut name = field("name")?;
+            let position = json_position(jsonfile, &text, &mut cursor, &name);
+            if name.is_empty() || name == ROOT {
+                self.report(TodoError::ReservedName.at(&position))?;
+                continue;
+            } else if name.starts_with('@') {
+                self.report(TodoError::AtInName { name }.at(&position))?;
+                continue;
+            }
+            if !name.contains('@') {
+                name.push_str(suffix);
+            }
+            let status = match field("status")?.as_str() {
+                "Completed" => Status::Completed,
+                "InProgress" => Status::InProgress,
+                "Blocked" => Status::Blocked,
+                "Cancelled" => Status::Cancelled,
+                "Waiting" => Status::Waiting,
+                _ => Status::Pending,
+            };
+            let dependencies: Vec<String> = strings("dependencies")?
+                .into_iter()
+                .map(|s| {
+                    if s.contains('@') {
+                        s
+                    } else {
+                        format!("{s}{suffix}")
+                    }
+                })
+                .collect();
+            if dependencies.iter().any(|d| d.replace("~", "") == name) {
+                self.report(
+                    TodoError::SelfDependency {
+                        name: name.to_string(),
+                    }
+                    .at(&position),
+                )?;
+                continue;
+            }
+            let (due, start) = (date("due")?, date("start")?);
+            let tags = match obj["tags"].is_null() {
+                true => Vec::new(),
+                false => strings("tags")?,
+            };
+            let recipe = match obj["recipe"].is_null() {
+                true => Vec::new(),
+                false => strings("recipe")?,
+            };
+            let priority = match &obj["priority"] {
+                Value::Null => None,
+                Value::String(s) => Some(s.parse::<Priority>()?),
+                _ => return Err(invalid("'priority' is not a string")),
+            };
+            let estimate = match &obj["estimate"] {
+                Value::Null => None,
+                Value::String(s) => Some(s.parse::<Estimate>()?),
+                _ => return Err(invalid("'estimate' is not a string")),
+            };
+            let owners = match version {
+                1 => Some(field("owner")?)
+                    .filter(|o| !o.is_empty())
+                    .into_iter()
+                    .collect(),
+                _ => strings("owners")?,
+            };
+            let comments = match self.opts.hide_comment {
+                true => Vec::new(),
+                false => strings("comments")?,
+            };
+            let todo = Todo::new(
+                name,
+                status,
+                owners,
+                comments,
+                dependencies,
+                strings("auxiliaries")?,
+            )
+            .map(|t| t.dated(due, start).prioritized(priority).tagged(tags))
+            .map(|t| t.estimated(estimate).scripted(recipe))
+            .map(|t| t.located(position.clone(), BTreeMap::new()))
+            .map_err(|e| e.at(&position));
+            self.new_todo_if_any(todo, jsonfile, dict, list)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the todos that are defined in dependencies only.
+    fn check_todos_in_dep_only(
+        &mut self,
+        auto_add: bool,
+        dict: &mut BTreeMap<String, Rc<RefCell<Todo>>>,
+    ) -> Result<(), TodoError> {
+        let mut noparent: BTreeSet<String> = dict.keys().cloned().collect();
+        let mut todoindepsonly: BTreeMap<String, (String, Todo)> =
+            BTreeMap::new();
+        for (key, todo) in dict.iter() {
+            for dep_raw in &todo.borrow().dependencies {
+                let dep_nom = dep_raw.replace("~", "").trim().to_string();
+                noparent.remove(&dep_nom);
+                let cur_completed = dep_raw.contains("~");
+                if dict.contains_key(&dep_nom) {
+                    if cur_completed {
+                        self.report(
+                            TodoError::CompletedDependency {
+                                name: dep_nom.to_string(),
+                                parent: key.to_string(),
+                            }
+                            .at(todo.borrow().dep_position(&dep_nom)),
+                        )?;
+                    }
+                    continue;
+                }
+                match todoindepsonly.get(&dep_nom) {
+                    Some(parent_todo) => {
+                        let prv_completed =
+                            parent_todo.1.status == Status::Completed;
+                        if prv_completed != cur_completed {
+                            self.report(
+                                TodoError::InconsistentDependency {
+                                    name: key.to_string(),
+                                    dependency: dep_nom.to_string(),
+                                    other: parent_todo.0.to_string(),
+                                }
+                                .at(todo.borrow().dep_position(&dep_nom)),
+                            )?;
+                        }
+                    }
+                    None => {
+                        if auto_add {
+                            let dep_status = if dep_raw.starts_with("~") {
+                                Status::Completed
+                            } else {
+                                Status::Pending
+                            };
+                            todoindepsonly.insert(
+                                dep_nom.clone(),
+                                (
+                                    key.to_string(),
+                                    Todo::new(
+                                        dep_nom,
+                                        dep_status,
+                                        Vec::new(),
+                                        Vec::new(),
+                                        Vec::new(),
+                                        Vec::new(),
+                                    )?,
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        for (k, v) in todoindepsonly {
+            dict.insert(k, Rc::new(RefCell::new(v.1)));
+        }
+        Ok(())
+    }
+
+    /// Creates todo
+    ///
+    /// When readmd reads the second todo or reaches the markdown file end.
+    /// An invalid todo is skipped if all errors are being checked.
+    fn new_todo_if_any(
+        &mut self,
+        todo: Result<Todo, TodoError>,
+        mdfile: &str,
+        dict: &mut BTreeMap<String, Rc<RefCell<Todo>>>,
+        list: &mut Vec<String>,
+    ) -> Result<(), TodoError> {
+        let mut todo = match todo {
+            Ok(todo) => todo,
+            Err(e) => return self.report(e),
+        };
+        if todo.name.is_empty() {
+            self.auxilaries = todo.auxilaries;
+            if todo.owners.is_empty()
+                && todo.status == Status::Pending
+                && todo.comment.is_empty()
+                && todo.dependencies.is_empty()
+                && todo.due.is_none()
+                && todo.start.is_none()
+                && todo.priority.is_none()
+                && todo.estimate.is_none()
+                && todo.tags.is_empty()
+                && todo.recipe.is_empty()
+            {
+                return Ok(());
+            } else {
+                return self
+                    .report(TodoError::MissingHeading.at(&todo.position));
+            }
+        }
+        if let Some(team) = todo.name.strip_prefix('@') {
+            let members = self.teams.entry(team.to_string()).or_default();
+            for owner in todo.owners {
+                if !members.contains(&owner) {
+                    members.push(owner);
+                }
+            }
+            return Ok(());
+        }
+        todo.file = mdfile.to_string();
+        if self.opts.separator != "\n" {
+            todo.comment = vec![todo.comment.join(&self.opts.separator); 1];
+        }
+        if self.opts.sort {
+            todo.dependencies.sort_by_key(|p| p.replace("~", ""));
+        }
+        let nm = todo.name.clone();
+        if dict.contains_key(&nm) {
+            return self.report(
+                TodoError::Duplicated {
+                    name: nm.to_string(),
+                }
+                .at(&todo.position),
+            );
+        }
+        dict.insert(nm.clone(), Rc::new(RefCell::new(todo)));
+        list.push(nm);
+        Ok(())
+    }
+}
+
+/// Spreads the priority of each todo to the todos it depends on.
+fn spread_priorities(dict: &BTreeMap<String, Rc<RefCell<Todo>>>) {
+    fn spread(
+        dict: &BTreeMap<String, Rc<RefCell<Todo>>>,
+        todo: &Rc<RefCell<Todo>>,
+        priority: Priority,
+    ) {
+        for dep in &todo.borrow().dependencies {
+            let Some(child) = dict.get(&dep.replace("~", "")) else {
+                continue;
+            };
+            let higher = child.borrow().effective.is_none_or(|p| p > priority);
+            if higher {
+                child.borrow_mut().effective = Some(priority);
+                spread(dict, child, priority);
+            }
+        }
+    }
+    for todo in dict.values() {
+        let priority = todo.borrow().priority;
+        if let Some(priority) = priority {
+            spread(dict, todo, priority);
+        }
+    }
+}
+
+/// Returns the elementary dependency loops, by Johnson's algorithm.
+///
+/// Each loop starts at its first todo in list, which depends on the second
+/// one, and so on, while the last one depends on the first one.
+fn find_cycles(
+    dict: &BTreeMap<String, Rc<RefCell<Todo>>>,
+    list: &[String],
+) -> Vec<Vec<String>> {
+    /// Finds the loops through v back to s, among todos not before s.
+    fn circuit(
+        v: usize,
+        s: usize,
+        adj: &[Vec<usize>],
+        blocked: &mut [bool],
+        blocks: &mut [Vec<usize>],
+        stack: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) -> bool {
+        let mut found = false;
+        stack.push(v);
+        blocked[v] = true;
+        for &w in adj[v].iter().filter(|w| **w >= s) {
+            if w == s {
+                cycles.push(stack.clone());
+                found = true;
+            } else if !blocked[w]
+                && circuit(w, s, adj, blocked, blocks, stack, cycles)
+            {
+                found = true;
+            }
+        }
+        if found {
+            unblock(v, blocked, blocks);
+        } else {
+            for &w in adj[v].iter().filter(|w| **w >= s) {
+                if !blocks[w].contains(&v) {
+                    blocks[w].push(v);
+                }
+            }
+        }
+        stack.pop();
+        found
+    }
+    fn unblock(u: usize, blocked: &mut [bool], blocks: &mut [Vec<usize>]) {
+        blocked[u] = false;
+        while let Some(w) = blocks[u].pop() {
+            if blocked[w] {
+                unblock(w, blocked, blocks);
+            }
+        }
+    }
+    let index: BTreeMap<&String, usize> =
+        list.iter().enumerate().map(|(i, nm)| (nm, i)).collect();
+    let adj: Vec<Vec<usize>> = list
+        .iter()
+        .map(|nm| {
+            let mut deps: Vec<usize> = dict[nm]
+                .borrow()
+                .dependencies
+                .iter()
+                .filter_map(|d| index.get(&d.replace("~", "")).copied())
+                .collect();
+            deps.sort_unstable();
+            deps.dedup();
+            deps
+        })
+        .collect();
+    let mut cycles: Vec<Vec<usize>> = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    for s in 0..list.len() {
+        let mut blocked = vec![false; list.len()];
+        let mut blocks: Vec<Vec<usize>> = vec![Vec::new(); list.len()];
+        circuit(
+            s,
+            s,
+            &adj,
+            &mut blocked,
+            &mut blocks,
+            &mut stack,
+            &mut cycles,
+        );
+    }
+    cycles
+        .into_iter()
+        .map(|c| c.into_iter().map(|i| list[i].clone()).collect())
+        .collect()
+}
+
+/// Returns the strings of a JSON array, or None if it is not one.
+fn json_strings(value: &Value) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|v| v.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// escape markdown string
+/// Returns the position of the '"name": ' value of a todo in a json text,
+/// searching from the end of the previous todo's.
+fn json_position(
+    file: &str,
+    text: &str,
+    cursor: &mut usize,
+    name: &str,
+) -> Position {
+    let quoted = json_quote(name);
+    let mut from = *cursor;
+    while let Some(idx) = text[from..].find(&quoted) {
+        let start = from + idx;
+        from = start + quoted.len();
+        let key = text[..start].trim_end().strip_suffix(':');
+        if key.is_some_and(|k| k.trim_end().ends_with("\"name\"")) {
+            let first = text[..start].rfind('\n').map_or(0, |i| i + 1);
+            let last =
+                text[start..].find('\n').map_or(text.len(), |i| start + i);
+            let line = text[..start].matches('\n').count() + 1;
+            *cursor = from;
+            return Position::new(file, line, &text[first..last], &quoted);
+        }
+    }
+    Position::default()
+}
+
+pub(crate) fn escape(input: &str) -> String {
+    static SPECIALS: [char; 15] = [
+        '\\', '`', '*', '_', '{', '}', '[', ']', '(', ')', '#', '+', '-', '.',
+        '!',
+    ];
+    let mut escaped = String::new();
+    let mut prev_is_slash = false;
+    for mut c in input.chars() {
+        if c == '\t' {
+            c = ' ';
+        }
+        if prev_is_slash {
+            if !SPECIALS.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            prev_is_slash = false;
+        } else if c == '\\' {
+            prev_is_slash = true;
+        } else {
+            escaped.push(c);
+        }
+    }
+    if prev_is_slash {
+        escaped.push('\\');
+    }
+    escaped
+}
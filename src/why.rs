@@ -0,0 +1,124 @@
+//! Explains why a todo is not actionable.
+use super::{Status, TodoError, edit, todo::Todo, tree::TreeOptions};
+use std::cell::{Ref, RefCell};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+
+/// An uncompleted todo whose dependencies are all completed, blocking another
+/// todo
+pub struct Blocker {
+    todo: Rc<RefCell<Todo>>,
+    /// the todos from the blocked one down to this one
+    chain: Vec<String>,
+}
+
+impl Blocker {
+    /// Returns the blocking todo.
+    pub fn todo(&self) -> Ref<'_, Todo> {
+        self.todo.borrow()
+    }
+
+    /// Returns the shortest dependency chain from the blocked todo to this
+    /// one.
+    pub fn chain(&self) -> &[String] {
+        &self.chain
+    }
+}
+
+/// A todo and the todos to complete before it is actionable
+pub struct Why {
+    todo: Rc<RefCell<Todo>>,
+    blockers: Vec<Blocker>,
+}
+
+impl Why {
+    /// Returns the todo.
+    pub fn todo(&self) -> Ref<'_, Todo> {
+        self.todo.borrow()
+    }
+
+    /// Returns the blockers, the ones with the shortest chains first.
+    pub fn blockers(&self) -> &[Blocker] {
+        &self.blockers
+    }
+}
+
+impl fmt::Display for Why {
+    fn fmt(&self, fo: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let todo = self.todo.borrow();
+        write!(fo, "{} is {}", todo.name, todo.status)?;
+        if self.blockers.is_empty() {
+            return match (todo.status, todo.start) {
+                (Status::Pending, Some(start)) => {
+                    writeln!(fo, ", it starts on {}", start)
+                }
+                (Status::Blocked | Status::Waiting, _) => {
+                    writeln!(fo, ", as its '- * ' line says")
+                }
+                _ => writeln!(fo, ", nothing blocks it"),
+            };
+        }
+        let count = self.blockers.len();
+        let plural = if count == 1 { "" } else { "s" };
+        writeln!(fo, ", blocked by {} todo{}", count, plural)?;
+        for blocker in &self.blockers {
+            let leaf = blocker.todo.borrow();
+            let mut details = vec![leaf.status.to_string()];
+            if let (Status::Pending, Some(start)) = (leaf.status, leaf.start) {
+                details.push(format!("starts {}", start));
+            }
+            if !leaf.owners.is_empty() {
+                details.push(leaf.owners.join(", "));
+            }
+            writeln!(
+                fo,
+                "- {} ({}): {}",
+                leaf.name,
+                details.join(", "),
+                blocker.chain.join(" -> ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the uncompleted todos, with all their dependencies completed, that
+/// a todo waits for, and the shortest chain to each one.
+pub fn why(opts: &TreeOptions, name: &str) -> Result<Why, TodoError> {
+    let tree = edit::load(opts)?;
+    let todos = edit::by_name(&tree);
+    let todo = Rc::clone(edit::find(&todos, name)?);
+    let name = todo.borrow().name.clone();
+    let mut chains: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    chains.insert(name.clone(), vec![name.clone()]);
+    let mut queue = VecDeque::from([name.clone()]);
+    let mut blockers = Vec::new();
+    while let Some(nm) = queue.pop_front() {
+        let current = Rc::clone(&todos[&nm]);
+        let mut waiting = false;
+        for dep in &current.borrow().dependencies {
+            let dep = dep.replace("~", "");
+            let todo = todos
+                .get(&dep)
+                .ok_or_else(|| TodoError::Missing { name: dep.clone() })?;
+            if todo.borrow().status.is_done() {
+                continue;
+            }
+            waiting = true;
+            if !chains.contains_key(&dep) {
+                let mut chain = chains[&nm].clone();
+                chain.push(dep.clone());
+                chains.insert(dep.clone(), chain);
+                queue.push_back(dep);
+            }
+        }
+        if !waiting && nm != name {
+            blockers.push(Blocker {
+                todo: Rc::clone(&current),
+                chain: chains[&nm].clone(),
+            });
+        }
+    }
+    Ok(Why { todo, blockers })
+}